@@ -83,7 +83,7 @@ fn main() -> ! {
     // read OFFSET
     match stepper_driver.read_offset() {
         Ok(offset) => {
-            sprintln!(in_out, "Stepper driver offset is {}", offset);
+            sprintln!(in_out, "Stepper driver offset is A: {}, B: {}", offset.phase_a_signed(), offset.phase_b_signed());
         }
         Err(error) => {
             sprintln!(in_out, "Error for reading offset is {:?}", error);
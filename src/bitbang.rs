@@ -0,0 +1,115 @@
+//! Bit-banged SPI for boards without a usable hardware SPI
+//!
+//! Implements the blocking SPI traits on GPIO pins in SPI mode 3 (see `MODE`), so it can be
+//! used as the `SPI` of `Tmc5160`. CS stays with the driver.
+use embedded_hal::{
+    blocking::delay::DelayUs,
+    blocking::spi::{Transfer, Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+/// Error of the bit-banged SPI
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BitBangError {
+    /// setting SCK or MOSI failed
+    Output,
+    /// reading MISO failed
+    Input,
+}
+
+/// SPI mode 3 on GPIO pins
+pub struct BitBangSpi<SCK, MOSI, MISO, D> {
+    sck: SCK,
+    mosi: MOSI,
+    miso: MISO,
+    delay: D,
+    half_period_us: u32,
+}
+
+impl<SCK, MOSI, MISO, D> BitBangSpi<SCK, MOSI, MISO, D>
+where
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+    D: DelayUs<u32>,
+{
+    /// create a bit-banged SPI, SCK is set to its idle level (high)
+    ///
+    /// `half_period_us` is the time between two clock edges, the SCK frequency is
+    /// `1 / (2 * half_period_us)` at most.
+    pub fn new(
+        mut sck: SCK,
+        mosi: MOSI,
+        miso: MISO,
+        delay: D,
+        half_period_us: u32,
+    ) -> Result<Self, BitBangError> {
+        sck.set_high().map_err(|_| BitBangError::Output)?;
+        Ok(BitBangSpi {
+            sck,
+            mosi,
+            miso,
+            delay,
+            half_period_us,
+        })
+    }
+
+    /// release the pins and the delay
+    pub fn free(self) -> (SCK, MOSI, MISO, D) {
+        (self.sck, self.mosi, self.miso, self.delay)
+    }
+
+    fn transfer_byte(&mut self, byte: u8) -> Result<u8, BitBangError> {
+        let mut received = 0;
+        for bit in (0..8).rev() {
+            // mode 3: data changes on the falling edge and is sampled on the rising edge
+            self.sck.set_low().map_err(|_| BitBangError::Output)?;
+            if byte & (1 << bit) != 0 {
+                self.mosi.set_high().map_err(|_| BitBangError::Output)?;
+            } else {
+                self.mosi.set_low().map_err(|_| BitBangError::Output)?;
+            }
+            self.delay.delay_us(self.half_period_us);
+            self.sck.set_high().map_err(|_| BitBangError::Output)?;
+            if self.miso.is_high().map_err(|_| BitBangError::Input)? {
+                received |= 1 << bit;
+            }
+            self.delay.delay_us(self.half_period_us);
+        }
+        Ok(received)
+    }
+}
+
+impl<SCK, MOSI, MISO, D> Transfer<u8> for BitBangSpi<SCK, MOSI, MISO, D>
+where
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+    D: DelayUs<u32>,
+{
+    type Error = BitBangError;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], BitBangError> {
+        for word in words.iter_mut() {
+            *word = self.transfer_byte(*word)?;
+        }
+        Ok(words)
+    }
+}
+
+impl<SCK, MOSI, MISO, D> Write<u8> for BitBangSpi<SCK, MOSI, MISO, D>
+where
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+    D: DelayUs<u32>,
+{
+    type Error = BitBangError;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), BitBangError> {
+        for word in words {
+            self.transfer_byte(*word)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! Chip variants supported by the driver
+//!
+//! The TMC5161 and TMC5130 share the register interface of the TMC5160. The variant is selected at
+//! compile time with the `tmc5161` or `tmc5130` feature, without a feature the TMC5160 is assumed.
+//!
+//! * TMC5161: MOSFETs are integrated, keep the DRV_CONF defaults.
+//! * TMC5130: MOSFETs and the full scale switch (CHOPCONF.vsense) are integrated, there is no
+//!   GLOBALSCALER, SHORT_CONF, DRV_CONF, OFFSET_READ, FACTORY_CONF, OTP or PWM_AUTO register.
+//!   GCONF bits 0, 1 and 3 are I_scale_analog, internal_Rsense and enc_commutation instead of
+//!   recalibrate, faststandstill and multistep_filt, so `recalibrate()`, `set_faststandstill()`
+//!   and `set_multistep_filt()` return `Error::Unsupported`. PWMCONF has pwm_symmetric at bit 19
+//!   and no pwm_reg/pwm_lim, PWM_SCALE has no pwm_scale_auto and DRV_STATUS has no s2vsa, s2vsb
+//!   or stealth flag. The TMC5160 meaning of these bits does not apply, the `SaturationMonitor`
+//!   returns `Error::Unsupported`.
+use crate::registers::Registers;
+
+#[cfg(all(feature = "tmc5161", feature = "tmc5130"))]
+compile_error!("the features `tmc5161` and `tmc5130` are mutually exclusive");
+
+/// Chip variant
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Chip {
+    /// TMC5160 with external MOSFETs
+    Tmc5160,
+    /// TMC5161 with integrated MOSFETs
+    Tmc5161,
+    /// TMC5130 with integrated MOSFETs and without the TMC5160 extensions
+    Tmc5130,
+}
+
+/// chip variant selected by the features
+#[cfg(not(any(feature = "tmc5161", feature = "tmc5130")))]
+pub const CHIP: Chip = Chip::Tmc5160;
+/// chip variant selected by the features
+#[cfg(feature = "tmc5161")]
+pub const CHIP: Chip = Chip::Tmc5161;
+/// chip variant selected by the features
+#[cfg(feature = "tmc5130")]
+pub const CHIP: Chip = Chip::Tmc5130;
+
+impl Chip {
+    /// check if the chip has the register at `addr`
+    pub fn has_register(self, addr: u8) -> bool {
+        match self {
+            Chip::Tmc5160 | Chip::Tmc5161 => true,
+            Chip::Tmc5130 => {
+                !matches!(
+                    addr,
+                    0x06..=0x0C // OTP_PROG, OTP_READ, FACTORY_CONF, SHORT_CONF, DRV_CONF, GLOBALSCALER, OFFSET_READ
+                ) && addr != Registers::PWM_AUTO as u8
+            }
+        }
+    }
+
+    /// check if the chip drives external MOSFETs
+    pub fn has_external_mosfets(self) -> bool {
+        self == Chip::Tmc5160
+    }
+
+    /// full scale sense resistor voltage in V
+    ///
+    /// `vsense` is the CHOPCONF.vsense bit, which only exists on the TMC5130.
+    pub fn full_scale_voltage(self, vsense: bool) -> f32 {
+        match self {
+            Chip::Tmc5130 if vsense => 0.180,
+            _ => 0.325,
+        }
+    }
+
+    /// mask of the bits of the register at `addr` that may be written
+    ///
+    /// The mask is built from `Registers::fields()`, so unused and reserved bits are cleared.
+    /// Read-only registers and registers that do not exist on the chip have a mask of 0. Value
+    /// registers narrower than 32 bits, e.g. VMAX, only allow their width.
+    pub fn valid_bits(self, addr: u8) -> u32 {
+        let register = match Registers::from_addr(addr & 0x7F) {
+            Some(register)
+                if register.access().is_writable() && self.has_register(register as u8) =>
+            {
+                register
+            }
+            _ => return 0,
+        };
+        let mask = register
+            .fields()
+            .iter()
+            .fold(0, |mask, field| mask | field.mask());
+        match register {
+            // OUTPUT when written
+            Registers::IOIN => 0x0000_0001,
+            // write 1 to clear: status_latch_l/r, event_stop_sg, event_pos_reached, second_move
+            Registers::RAMP_STAT => 0x0000_10CC,
+            // bit 13 (rndtf) and bit 17 (vsense) only exist on the TMC5130
+            Registers::CHOPCONF if self == Chip::Tmc5130 => mask | 1 << 13,
+            Registers::CHOPCONF => mask & !(1 << 17),
+            _ => mask,
+        }
+    }
+}
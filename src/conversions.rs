@@ -0,0 +1,66 @@
+//! Conversion between physical units and the ramp generator registers
+//!
+//! Implements the scaling of the datasheet (section "Real world unit conversions"), with
+//! velocities in microsteps per second and accelerations in microsteps per second squared:
+//!
+//! * velocity: `v[Hz] = v[5160] * fCLK / 2 / 2^23`
+//! * acceleration: `a[Hz/s] = a[5160] * fCLK^2 / (512 * 256) / 2^24`
+//!
+//! The results are not rounded or range checked, the register setters of the driver validate them.
+
+/// 2^24
+const TWO_POW_24: f32 = 16_777_216.0;
+
+/// velocity register value (VMAX, VSTART, V1, ...) for a velocity in microsteps/s
+///
+/// ```
+/// use tmc5160::conversions::velocity_to_register;
+/// // 50 kHz microstep frequency at the internal 12 MHz clock
+/// assert!((velocity_to_register(50_000.0, 12_000_000.0) - 69_905.07).abs() < 0.1);
+/// ```
+pub fn velocity_to_register(velocity: f32, clock: f32) -> f32 {
+    velocity / clock * TWO_POW_24
+}
+
+/// velocity in microsteps/s for a velocity register value
+///
+/// ```
+/// use tmc5160::conversions::velocity_from_register;
+/// // the maximum VMAX of 2^23 - 512 at 12 MHz is close to 6 MHz microstep frequency
+/// assert!((velocity_from_register(8_388_096.0, 12_000_000.0) - 5_999_633.8).abs() < 1.0);
+/// ```
+pub fn velocity_from_register(velocity: f32, clock: f32) -> f32 {
+    velocity * clock / TWO_POW_24
+}
+
+/// acceleration register value (AMAX, DMAX, A1, D1) for an acceleration in microsteps/s²
+///
+/// ```
+/// use tmc5160::conversions::acceleration_to_register;
+/// // 1 revolution/s² with 200 fullsteps and 256 microsteps at 12 MHz
+/// assert!((acceleration_to_register(51_200.0, 12_000_000.0) - 781.87).abs() < 0.01);
+/// ```
+pub fn acceleration_to_register(acceleration: f32, clock: f32) -> f32 {
+    acceleration / clock / clock * (512.0 * 256.0) * TWO_POW_24
+}
+
+/// acceleration in microsteps/s² for an acceleration register value
+///
+/// ```
+/// use tmc5160::conversions::acceleration_from_register;
+/// // AMAX = 1000 at 12 MHz
+/// assert!((acceleration_from_register(1000.0, 12_000_000.0) - 65_483.6).abs() < 0.1);
+/// ```
+pub fn acceleration_from_register(acceleration: f32, clock: f32) -> f32 {
+    acceleration * clock * clock / (512.0 * 256.0) / TWO_POW_24
+}
+
+/// TSTEP value (clocks per 1/256 microstep) at a velocity register value, with 256 microsteps
+///
+/// ```
+/// use tmc5160::conversions::tstep_from_register;
+/// assert_eq!(tstep_from_register(1024.0), 16_384.0);
+/// ```
+pub fn tstep_from_register(velocity: f32) -> f32 {
+    TWO_POW_24 / velocity
+}
@@ -0,0 +1,114 @@
+//! Closed-loop position correction from the encoder
+//!
+//! At standstill the encoder position X_ENC is compared with XACTUAL. ENC_CONST has to be set up so
+//! X_ENC counts in microsteps, like XACTUAL. The hardware deviation warning (ENC_DEVIATION and
+//! ENC_STATUS.deviation_warn) detects lost steps during motion, the correction repairs the
+//! position once the motor has stopped.
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+
+use crate::registers::Registers;
+use crate::split::DriverAccess;
+use crate::{Error, Tmc5160};
+
+/// What `PositionCorrection` does when the deviation exceeds the threshold
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CorrectionPolicy {
+    /// set XACTUAL to X_ENC, in positioning mode the ramp generator then moves back to XTARGET
+    Move,
+    /// set XACTUAL and XTARGET to X_ENC, accepting the actual position without motion
+    Resync,
+}
+
+/// Correction performed by `PositionCorrection::update()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Correction {
+    /// XACTUAL before the correction
+    pub xactual: i32,
+    /// X_ENC, the new XACTUAL
+    pub x_enc: i32,
+    /// XACTUAL - X_ENC in microsteps
+    pub deviation: i32,
+    /// applied policy
+    pub policy: CorrectionPolicy,
+}
+
+/// Automatic position correction at standstill
+#[derive(Debug, Copy, Clone)]
+pub struct PositionCorrection {
+    /// deviation in microsteps above which the position is corrected
+    pub threshold: u32,
+    /// correction to apply
+    pub policy: CorrectionPolicy,
+    corrections: u32,
+}
+
+impl PositionCorrection {
+    /// create a new position correction
+    pub fn new(threshold: u32, policy: CorrectionPolicy) -> Self {
+        PositionCorrection {
+            threshold,
+            policy,
+            corrections: 0,
+        }
+    }
+
+    /// number of corrections performed
+    pub fn corrections(&self) -> u32 {
+        self.corrections
+    }
+
+    /// compare X_ENC with XACTUAL at standstill and correct the position if they deviate
+    ///
+    /// Returns the correction if one has been made. While the motor moves nothing is checked.
+    /// `CorrectionPolicy::Move` is rejected during an emergency stop.
+    pub fn update<A, SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut A,
+    ) -> Result<Option<Correction>, Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        driver.access(|driver| self.update_driver(driver))
+    }
+
+    fn update_driver<SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut Tmc5160<SPI, CS, EN>,
+    ) -> Result<Option<Correction>, Error<E>>
+    where
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        if !driver.read_drv_status()?.standstill() {
+            return Ok(None);
+        }
+        let xactual = driver.read_register(Registers::XACTUAL)?.data as i32;
+        let x_enc = driver.read_x_enc()?;
+        let deviation = xactual.wrapping_sub(x_enc);
+        if deviation.unsigned_abs() <= self.threshold {
+            return Ok(None);
+        }
+        if self.policy == CorrectionPolicy::Move {
+            driver.check_emergency_stop()?;
+        }
+        let mut value = x_enc.to_be_bytes();
+        driver.write_register(Registers::XACTUAL, &mut value)?;
+        if self.policy == CorrectionPolicy::Resync {
+            driver.write_register(Registers::XTARGET, &mut value)?;
+        }
+        self.corrections = self.corrections.wrapping_add(1);
+        Ok(Some(Correction {
+            xactual,
+            x_enc,
+            deviation,
+            policy: self.policy,
+        }))
+    }
+}
@@ -0,0 +1,118 @@
+//! Deadlines for blocking operations
+use embedded_hal::blocking::delay::DelayUs;
+
+/// Deadline of a blocking operation
+///
+/// Blocking helpers poll their condition, call `wait()` between the polls and return
+/// `Error::Timeout` once `expired()` reports true.
+pub trait Deadline {
+    /// check if the deadline has passed
+    fn expired(&mut self) -> bool;
+
+    /// wait before the next poll
+    fn wait(&mut self);
+}
+
+/// Deadline based on a delay provider
+///
+/// Only the time spent waiting is counted, the time of the SPI transfers is not accounted.
+pub struct DelayDeadline<'a, D> {
+    delay: &'a mut D,
+    remaining_us: u32,
+    poll_us: u32,
+}
+
+impl<'a, D: DelayUs<u32>> DelayDeadline<'a, D> {
+    /// create a deadline expiring after `timeout_ms`, polling every ms
+    pub fn new(delay: &'a mut D, timeout_ms: u32) -> Self {
+        DelayDeadline {
+            delay,
+            remaining_us: timeout_ms.saturating_mul(1000),
+            poll_us: 1000,
+        }
+    }
+
+    /// specify the poll interval in µs (Default is 1000)
+    pub fn poll_interval_us(mut self, poll_us: u32) -> Self {
+        self.poll_us = poll_us.max(1);
+        self
+    }
+}
+
+impl<D: DelayUs<u32>> Deadline for DelayDeadline<'_, D> {
+    fn expired(&mut self) -> bool {
+        self.remaining_us == 0
+    }
+
+    fn wait(&mut self) {
+        let poll_us = self.poll_us.min(self.remaining_us);
+        self.delay.delay_us(poll_us);
+        self.remaining_us -= poll_us;
+    }
+}
+
+/// Deadline based on a monotonic millisecond tick source of the application
+pub struct TickDeadline<F> {
+    now_ms: F,
+    start: u32,
+    timeout_ms: u32,
+}
+
+impl<F: FnMut() -> u32> TickDeadline<F> {
+    /// create a deadline expiring `timeout_ms` after now
+    pub fn new(mut now_ms: F, timeout_ms: u32) -> Self {
+        let start = now_ms();
+        TickDeadline {
+            now_ms,
+            start,
+            timeout_ms,
+        }
+    }
+}
+
+impl<F: FnMut() -> u32> Deadline for TickDeadline<F> {
+    fn expired(&mut self) -> bool {
+        (self.now_ms)().wrapping_sub(self.start) >= self.timeout_ms
+    }
+
+    fn wait(&mut self) {
+        core::hint::spin_loop();
+    }
+}
+
+/// Deadline of the async waits based on the embassy-time clock
+///
+/// Used by the `*_async` helpers of the driver instead of a `Deadline`. Between the polls the
+/// helpers await `embassy_time::Timer`, so other tasks of the executor run while waiting.
+#[cfg(feature = "embassy")]
+pub struct TimerDeadline {
+    expires: embassy_time::Instant,
+    poll: embassy_time::Duration,
+}
+
+#[cfg(feature = "embassy")]
+impl TimerDeadline {
+    /// create a deadline expiring `timeout` after now, polling every ms
+    pub fn new(timeout: embassy_time::Duration) -> Self {
+        TimerDeadline {
+            expires: embassy_time::Instant::now() + timeout,
+            poll: embassy_time::Duration::from_millis(1),
+        }
+    }
+
+    /// specify the poll interval (Default is 1 ms)
+    pub fn poll_interval(mut self, poll: embassy_time::Duration) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    /// check if the deadline has passed
+    pub fn expired(&self) -> bool {
+        embassy_time::Instant::now() >= self.expires
+    }
+
+    /// wait for the poll interval without blocking the executor
+    pub async fn wait(&self) {
+        embassy_time::Timer::after(self.poll).await
+    }
+}
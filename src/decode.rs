@@ -0,0 +1,56 @@
+//! Decoding of raw register values for host side tools
+//!
+//! Turns `(address, value)` pairs, e.g. from a log or `Tmc5160::dump_registers()`, into readable
+//! register descriptions using `Registers::fields()`, the layout of the structs in `registers`.
+extern crate std;
+
+use core::fmt::Write;
+use std::string::String;
+
+pub use crate::registers::Field;
+use crate::registers::Registers;
+
+/// name of the register at `addr`
+pub fn register_name(addr: u8) -> Option<&'static str> {
+    Registers::from_addr(addr & 0x7F).map(Registers::name)
+}
+
+/// fields of the register at `addr`, empty for unknown addresses
+pub fn fields(addr: u8) -> &'static [Field] {
+    Registers::from_addr(addr & 0x7F).map_or(&[], Registers::fields)
+}
+
+/// decode a register value into a single line, e.g. `IHOLD_IRUN (0x10) = 0x00061008 { i_hold: 8, i_run: 16, i_hold_delay: 6 }`
+pub fn decode(addr: u8, value: u32) -> String {
+    let mut out = String::new();
+    let addr = addr & 0x7F;
+    let name = register_name(addr).unwrap_or("UNKNOWN");
+    write!(out, "{} (0x{:02X}) = 0x{:08X}", name, addr, value).ok();
+    match fields(addr) {
+        [] => {}
+        [field] if field.name == "value" => {
+            write!(out, " ({})", field.extract(value)).ok();
+        }
+        fields => {
+            out.push_str(" {");
+            for (i, field) in fields.iter().enumerate() {
+                let separator = if i == 0 { " " } else { ", " };
+                write!(out, "{}{}: {}", separator, field.name, field.extract(value)).ok();
+            }
+            out.push_str(" }");
+        }
+    }
+    out
+}
+
+/// decode a register value into one line per field, for multi line output
+pub fn decode_pretty(addr: u8, value: u32) -> String {
+    let mut out = String::new();
+    let addr = addr & 0x7F;
+    let name = register_name(addr).unwrap_or("UNKNOWN");
+    writeln!(out, "{} (0x{:02X}) = 0x{:08X}", name, addr, value).ok();
+    for field in fields(addr) {
+        writeln!(out, "    {:<24}{}", field.name, field.extract(value)).ok();
+    }
+    out
+}
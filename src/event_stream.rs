@@ -0,0 +1,154 @@
+//! Asynchronous source of status events woken by the DIAG pins
+//!
+//! The interrupt handler of the DIAG0 and/or DIAG1 pin calls `DiagSignal::signal()`. The
+//! `EventStream` then reads RAMP_STAT and DRV_STATUS on its next poll and yields the raised
+//! `StatusEvent`s one by one:
+//!
+//! ```ignore
+//! static DIAG: DiagSignal = DiagSignal::new();
+//!
+//! let mut events = EventStream::new(&mut driver, &DIAG);
+//! while let Some(event) = events.next().await {
+//!     match event? {
+//!         StatusEvent::EventPosReached => {}
+//!         StatusEvent::EventStopSg => {}
+//!         _ => {}
+//!     }
+//! }
+//! ```
+//!
+//! GCONF has to route the wanted conditions to the pins, e.g. diag0_error and diag0_stall.
+//! `poll_next()` has the signature of `futures::Stream::poll_next()`, so the source can be
+//! wrapped in a `Stream`.
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+
+use crate::events::{StatusEvent, StatusEvents};
+use crate::split::DriverAccess;
+use crate::{Error, Tmc5160};
+
+/// Wakeup from the DIAG pin interrupt
+pub struct DiagSignal {
+    pending: critical_section::Mutex<Cell<bool>>,
+    waker: critical_section::Mutex<RefCell<Option<Waker>>>,
+}
+
+impl DiagSignal {
+    /// create a signal, the first poll of a stream always reads the status
+    pub const fn new() -> Self {
+        DiagSignal {
+            pending: critical_section::Mutex::new(Cell::new(true)),
+            waker: critical_section::Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// signal a DIAG pin edge, to be called from the interrupt handler
+    pub fn signal(&self) {
+        let waker = critical_section::with(|cs| {
+            self.pending.borrow(cs).set(true);
+            self.waker.borrow_ref_mut(cs).take()
+        });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// take the pending signal, registering `waker` if there is none
+    fn take(&self, waker: &Waker) -> bool {
+        critical_section::with(|cs| {
+            if self.pending.borrow(cs).replace(false) {
+                return true;
+            }
+            let mut slot = self.waker.borrow_ref_mut(cs);
+            match slot.as_ref() {
+                Some(registered) if registered.will_wake(waker) => {}
+                _ => *slot = Some(waker.clone()),
+            }
+            false
+        })
+    }
+}
+
+impl Default for DiagSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Status events of a driver, read when the DIAG pins signal a change
+pub struct EventStream<'a, A> {
+    driver: &'a mut A,
+    signal: &'a DiagSignal,
+    pending: StatusEvents,
+}
+
+impl<'a, A, SPI, CS, EN, E> EventStream<'a, A>
+where
+    A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin,
+    EN: OutputPin,
+{
+    /// create a stream of the events of `driver`, woken by `signal`
+    pub fn new(driver: &'a mut A, signal: &'a DiagSignal) -> Self {
+        EventStream {
+            driver,
+            signal,
+            pending: StatusEvents::default(),
+        }
+    }
+
+    /// poll for the next event
+    ///
+    /// Events raised by one status read are returned before the status is read again. The stream
+    /// never ends, a failed status read is returned as `Some(Err(_))`.
+    pub fn poll_next(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<StatusEvent, Error<E>>>> {
+        loop {
+            if let Some(event) = self.pending.next() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if !self.signal.take(cx.waker()) {
+                return Poll::Pending;
+            }
+            match self.driver.access(|driver| driver.status_events()) {
+                Ok(events) => self.pending = events,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+
+    /// wait for the next event
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Next<'_, 'a, A> {
+        Next { stream: self }
+    }
+}
+
+/// Future returned by `EventStream::next()`
+pub struct Next<'s, 'a, A> {
+    stream: &'s mut EventStream<'a, A>,
+}
+
+impl<A, SPI, CS, EN, E> Future for Next<'_, '_, A>
+where
+    A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin,
+    EN: OutputPin,
+{
+    type Output = Option<Result<StatusEvent, Error<E>>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.stream.poll_next(cx)
+    }
+}
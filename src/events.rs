@@ -0,0 +1,140 @@
+//! Edge detected status events
+use crate::registers::*;
+
+/// Status event, raised when the corresponding flag changes from inactive to active
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StatusEvent {
+    /// the chip has been reset (SPI status reset_flag)
+    Reset,
+    /// the driver has been shut down due to overtemperature or a short circuit (SPI status driver_error)
+    DriverError,
+    /// stallGuard threshold reached (SPI status sg2)
+    Stall,
+    /// the motor reached standstill (SPI status standstill)
+    Standstill,
+    /// VACTUAL reached VMAX (SPI status velocity_reached)
+    VelocityReached,
+    /// XACTUAL reached XTARGET (SPI status position_reached)
+    PositionReached,
+    /// the left reference switch became active (SPI status status_stop_l)
+    StopL,
+    /// the right reference switch became active (SPI status status_stop_r)
+    StopR,
+    /// the motor was stopped by the left reference switch (RAMP_STAT event_stop_l)
+    EventStopL,
+    /// the motor was stopped by the right reference switch (RAMP_STAT event_stop_r)
+    EventStopR,
+    /// the motor was stopped by stallGuard (RAMP_STAT event_stop_sg)
+    EventStopSg,
+    /// the target position has been reached (RAMP_STAT event_pos_reached)
+    EventPosReached,
+    /// overtemperature shutdown (DRV_STATUS ot)
+    OverTemperature,
+    /// overtemperature pre-warning (DRV_STATUS otpw)
+    OverTemperatureWarning,
+    /// short to ground on phase A (DRV_STATUS s2ga)
+    ShortToGroundA,
+    /// short to ground on phase B (DRV_STATUS s2gb)
+    ShortToGroundB,
+    /// short to supply on phase A (DRV_STATUS s2vsa)
+    ShortToSupplyA,
+    /// short to supply on phase B (DRV_STATUS s2vsb)
+    ShortToSupplyB,
+    /// open load on phase A (DRV_STATUS ola)
+    OpenLoadA,
+    /// open load on phase B (DRV_STATUS olb)
+    OpenLoadB,
+}
+
+impl StatusEvent {
+    const ALL: [StatusEvent; 20] = [
+        StatusEvent::Reset,
+        StatusEvent::DriverError,
+        StatusEvent::Stall,
+        StatusEvent::Standstill,
+        StatusEvent::VelocityReached,
+        StatusEvent::PositionReached,
+        StatusEvent::StopL,
+        StatusEvent::StopR,
+        StatusEvent::EventStopL,
+        StatusEvent::EventStopR,
+        StatusEvent::EventStopSg,
+        StatusEvent::EventPosReached,
+        StatusEvent::OverTemperature,
+        StatusEvent::OverTemperatureWarning,
+        StatusEvent::ShortToGroundA,
+        StatusEvent::ShortToGroundB,
+        StatusEvent::ShortToSupplyA,
+        StatusEvent::ShortToSupplyB,
+        StatusEvent::OpenLoadA,
+        StatusEvent::OpenLoadB,
+    ];
+}
+
+/// combine the flags of the SPI status, RAMP_STAT and DRV_STATUS into one mask (bit n = `StatusEvent::ALL[n]`)
+pub(crate) fn status_mask(status: SpiStatus, ramp_stat: RampStat, drv_status: DrvStatus) -> u32 {
+    let flags = [
+        status.reset_flag(),
+        status.driver_error(),
+        status.sg2(),
+        status.standstill(),
+        status.velocity_reached(),
+        status.position_reached(),
+        status.status_stop_l(),
+        status.status_stop_r(),
+        ramp_stat.event_stop_l(),
+        ramp_stat.event_stop_r(),
+        ramp_stat.event_stop_sg(),
+        ramp_stat.event_pos_reached(),
+        drv_status.ot(),
+        drv_status.otpw(),
+        drv_status.s2ga(),
+        drv_status.s2gb(),
+        drv_status.s2vsa(),
+        drv_status.s2vsb(),
+        drv_status.ola(),
+        drv_status.olb(),
+    ];
+    flags
+        .iter()
+        .enumerate()
+        .fold(0, |mask, (i, &set)| mask | ((set as u32) << i))
+}
+
+/// Iterator over newly raised status events
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct StatusEvents {
+    mask: u32,
+}
+
+impl StatusEvents {
+    pub(crate) fn new(mask: u32) -> Self {
+        StatusEvents { mask }
+    }
+
+    /// check if no new event was raised
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    /// check if the given event was raised
+    pub fn contains(&self, event: StatusEvent) -> bool {
+        StatusEvent::ALL
+            .iter()
+            .position(|&e| e == event)
+            .is_some_and(|i| self.mask & (1 << i) != 0)
+    }
+}
+
+impl Iterator for StatusEvents {
+    type Item = StatusEvent;
+
+    fn next(&mut self) -> Option<StatusEvent> {
+        if self.mask == 0 {
+            return None;
+        }
+        let i = self.mask.trailing_zeros();
+        self.mask &= !(1 << i);
+        Some(StatusEvent::ALL[i as usize])
+    }
+}
@@ -0,0 +1,522 @@
+//! Fault handling and recovery
+use core::fmt;
+
+#[cfg(feature = "driver")]
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+
+#[cfg(feature = "driver")]
+use crate::chip::{Chip, CHIP};
+use crate::registers::*;
+#[cfg(feature = "driver")]
+use crate::split::DriverAccess;
+#[cfg(feature = "driver")]
+use crate::{swap_bytes, Error, Tmc5160};
+
+/// Set of driver faults from DRV_STATUS and GSTAT, carried by `Error::DriverFault`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct FaultFlags(u16);
+
+impl FaultFlags {
+    /// overtemperature shutdown (DRV_STATUS.ot)
+    pub const OVERTEMPERATURE: FaultFlags = FaultFlags(1 << 0);
+    /// short to ground on phase A (DRV_STATUS.s2ga)
+    pub const SHORT_TO_GROUND_A: FaultFlags = FaultFlags(1 << 1);
+    /// short to ground on phase B (DRV_STATUS.s2gb)
+    pub const SHORT_TO_GROUND_B: FaultFlags = FaultFlags(1 << 2);
+    /// short to supply on phase A (DRV_STATUS.s2vsa)
+    pub const SHORT_TO_SUPPLY_A: FaultFlags = FaultFlags(1 << 3);
+    /// short to supply on phase B (DRV_STATUS.s2vsb)
+    pub const SHORT_TO_SUPPLY_B: FaultFlags = FaultFlags(1 << 4);
+    /// charge pump undervoltage (GSTAT.uv_cp)
+    pub const CHARGE_PUMP_UNDERVOLTAGE: FaultFlags = FaultFlags(1 << 5);
+    /// driver shut down by a fault (GSTAT.drv_err)
+    pub const DRIVER_ERROR: FaultFlags = FaultFlags(1 << 6);
+
+    const NAMES: [(FaultFlags, &'static str); 7] = [
+        (FaultFlags::OVERTEMPERATURE, "ot"),
+        (FaultFlags::SHORT_TO_GROUND_A, "s2ga"),
+        (FaultFlags::SHORT_TO_GROUND_B, "s2gb"),
+        (FaultFlags::SHORT_TO_SUPPLY_A, "s2vsa"),
+        (FaultFlags::SHORT_TO_SUPPLY_B, "s2vsb"),
+        (FaultFlags::CHARGE_PUMP_UNDERVOLTAGE, "uv_cp"),
+        (FaultFlags::DRIVER_ERROR, "drv_err"),
+    ];
+
+    /// collect the fault flags of DRV_STATUS and GSTAT
+    pub fn from_registers(drv_status: DrvStatus, gstat: GStat) -> Self {
+        let flags = [
+            (drv_status.ot(), FaultFlags::OVERTEMPERATURE),
+            (drv_status.s2ga(), FaultFlags::SHORT_TO_GROUND_A),
+            (drv_status.s2gb(), FaultFlags::SHORT_TO_GROUND_B),
+            (drv_status.s2vsa(), FaultFlags::SHORT_TO_SUPPLY_A),
+            (drv_status.s2vsb(), FaultFlags::SHORT_TO_SUPPLY_B),
+            (gstat.uv_cp(), FaultFlags::CHARGE_PUMP_UNDERVOLTAGE),
+            (gstat.drv_err(), FaultFlags::DRIVER_ERROR),
+        ];
+        flags
+            .iter()
+            .filter(|(set, _)| *set)
+            .fold(FaultFlags::default(), |acc, (_, flag)| acc | *flag)
+    }
+
+    /// raw bits of the set
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    /// check if no fault is set
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// check if all faults of `other` are set
+    pub fn contains(&self, other: FaultFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for FaultFlags {
+    type Output = FaultFlags;
+
+    fn bitor(self, rhs: FaultFlags) -> FaultFlags {
+        FaultFlags(self.0 | rhs.0)
+    }
+}
+
+impl fmt::Display for FaultFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut any = false;
+        for (_, name) in Self::NAMES.iter().filter(|(flag, _)| self.contains(*flag)) {
+            if any {
+                f.write_str(" ")?;
+            }
+            f.write_str(name)?;
+            any = true;
+        }
+        if !any {
+            f.write_str("no faults")?;
+        }
+        Ok(())
+    }
+}
+
+/// State reported by `ShortRecovery::update()`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RecoveryState {
+    /// no short circuit detected
+    Ok,
+    /// short circuit detected, waiting for the cool-off time to pass
+    CoolingOff,
+    /// the power stage has been re-enabled after a short circuit
+    Recovered,
+    /// the retry budget is exhausted, the power stage stays disabled until `reset()`
+    Latched,
+}
+
+/// Recovery policy for short circuit faults (s2g and s2vs)
+///
+/// After a short circuit, the power stage is disabled for `cool_off_ms`, then the flags are
+/// cleared and the stage is re-enabled. After `max_retries` recoveries the fault is latched.
+#[cfg(feature = "driver")]
+#[derive(Debug, Copy, Clone)]
+pub struct ShortRecovery {
+    /// time in ms the power stage stays disabled after a short circuit
+    pub cool_off_ms: u32,
+    /// number of recoveries before the fault is latched
+    pub max_retries: u8,
+    retries: u8,
+    fault_since: Option<u32>,
+    latched: bool,
+}
+
+#[cfg(feature = "driver")]
+impl ShortRecovery {
+    /// create a new short circuit recovery policy
+    pub fn new(cool_off_ms: u32, max_retries: u8) -> Self {
+        ShortRecovery {
+            cool_off_ms,
+            max_retries,
+            retries: 0,
+            fault_since: None,
+            latched: false,
+        }
+    }
+
+    /// number of recoveries performed since the last `reset()`
+    pub fn retries(&self) -> u8 {
+        self.retries
+    }
+
+    /// check if the fault is latched
+    pub fn is_latched(&self) -> bool {
+        self.latched
+    }
+
+    /// clear a latched fault and the retry counter, the next `update()` re-enables the power stage
+    pub fn reset(&mut self) {
+        self.retries = 0;
+        self.latched = false;
+    }
+
+    /// check DRV_STATUS for short circuits and run the recovery
+    ///
+    /// `now_ms` is a monotonic millisecond timestamp of the application.
+    pub fn update<A, SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut A,
+        now_ms: u32,
+    ) -> Result<RecoveryState, Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        driver.access(|driver| self.update_driver(driver, now_ms))
+    }
+
+    fn update_driver<SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut Tmc5160<SPI, CS, EN>,
+        now_ms: u32,
+    ) -> Result<RecoveryState, Error<E>>
+    where
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        if self.latched {
+            return Ok(RecoveryState::Latched);
+        }
+        let since = match self.fault_since {
+            Some(since) => since,
+            None => {
+                let status = driver.read_drv_status()?;
+                if !(status.s2ga() || status.s2gb() || status.s2vsa() || status.s2vsb()) {
+                    return Ok(RecoveryState::Ok);
+                }
+                driver.disable()?;
+                self.fault_since = Some(now_ms);
+                now_ms
+            }
+        };
+        if now_ms.wrapping_sub(since) < self.cool_off_ms {
+            return Ok(RecoveryState::CoolingOff);
+        }
+        if self.retries >= self.max_retries {
+            self.latched = true;
+            return Ok(RecoveryState::Latched);
+        }
+        self.fault_since = None;
+        self.retries += 1;
+        // the short circuit flags are cleared by switching the driver off with TOFF = 0
+        let mut value = swap_bytes(driver.chop_conf.with_toff(0).into_bytes());
+        driver.write_register(Registers::CHOPCONF, &mut value)?;
+        driver.update_chop_conf()?;
+        driver.clear_g_stat()?;
+        driver.enable()?;
+        Ok(RecoveryState::Recovered)
+    }
+}
+
+/// Power stage health, maintained by `HealthMonitor`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Health {
+    /// faults reported by the last DRV_STATUS sample
+    pub active: FaultFlags,
+    /// the overtemperature pre-warning is active
+    pub otpw: bool,
+    /// time of the last sample with an active overtemperature pre-warning
+    pub last_otpw_ms: Option<u32>,
+    /// number of short circuit events (rising edges of s2ga, s2gb, s2vsa or s2vsb)
+    pub short_events: u32,
+    /// number of overtemperature shutdowns (rising edges of ot)
+    pub overtemperature_events: u32,
+    /// number of DRV_STATUS samples
+    pub samples: u32,
+}
+
+impl Health {
+    /// time in ms since the last overtemperature pre-warning, `None` if there was none
+    pub fn ms_since_otpw(&self, now_ms: u32) -> Option<u32> {
+        self.last_otpw_ms.map(|last| now_ms.wrapping_sub(last))
+    }
+}
+
+/// Monitor of the power stage health for predictive maintenance
+///
+/// `tick()` samples DRV_STATUS and updates the `Health`. Events are counted once per occurrence,
+/// so the sampling interval has to be shorter than the duration of a fault.
+#[cfg(feature = "driver")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct HealthMonitor {
+    health: Health,
+}
+
+#[cfg(feature = "driver")]
+impl HealthMonitor {
+    /// create a new health monitor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// health since the creation or the last `reset()`
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// clear the health statistics
+    pub fn reset(&mut self) {
+        self.health = Health::default();
+    }
+
+    /// sample DRV_STATUS and update the health
+    ///
+    /// `now_ms` is a monotonic millisecond timestamp of the application.
+    pub fn tick<A, SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut A,
+        now_ms: u32,
+    ) -> Result<&Health, Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        let status = driver.access(|driver| driver.read_drv_status())?;
+        self.update(status, now_ms);
+        Ok(&self.health)
+    }
+
+    fn update(&mut self, status: DrvStatus, now_ms: u32) {
+        const SHORTS: FaultFlags = FaultFlags(
+            FaultFlags::SHORT_TO_GROUND_A.0
+                | FaultFlags::SHORT_TO_GROUND_B.0
+                | FaultFlags::SHORT_TO_SUPPLY_A.0
+                | FaultFlags::SHORT_TO_SUPPLY_B.0,
+        );
+        let previous = self.health.active;
+        let active = FaultFlags::from_registers(status, GStat::new());
+        let health = &mut self.health;
+        if active.0 & SHORTS.0 & !previous.0 != 0 {
+            health.short_events = health.short_events.wrapping_add(1);
+        }
+        if active.contains(FaultFlags::OVERTEMPERATURE)
+            && !previous.contains(FaultFlags::OVERTEMPERATURE)
+        {
+            health.overtemperature_events = health.overtemperature_events.wrapping_add(1);
+        }
+        health.otpw = status.otpw();
+        if health.otpw {
+            health.last_otpw_ms = Some(now_ms);
+        }
+        health.active = active;
+        health.samples = health.samples.wrapping_add(1);
+    }
+}
+
+/// Likely broken motor wire, reported by `DisconnectMonitor::update()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DisconnectEvent {
+    /// open load on phase A
+    pub phase_a: bool,
+    /// open load on phase B
+    pub phase_b: bool,
+    /// SG_RESULT at the time of the detection
+    pub sg_result: u16,
+}
+
+/// Detection of a disconnected or broken motor wire during operation
+///
+/// The open load flags are only meaningful in spreadCycle at low to medium velocities, they are
+/// evaluated while |VACTUAL| is between `min_velocity` and `max_velocity` (raw register values).
+/// A wire break is reported when an open load flag persists for `persist_ms` while SG_RESULT is
+/// at least `sg_no_load`, i.e. the motor shows no load. Each disconnect is reported once, until
+/// the condition clears.
+#[cfg(feature = "driver")]
+#[derive(Debug, Copy, Clone)]
+pub struct DisconnectMonitor {
+    /// lowest |VACTUAL| at which the open load flags are evaluated
+    pub min_velocity: u32,
+    /// highest |VACTUAL| at which the open load flags are evaluated
+    pub max_velocity: u32,
+    /// SG_RESULT (0 to 1023) from which the motor is considered unloaded
+    pub sg_no_load: u16,
+    /// time in ms the open load indication has to persist
+    pub persist_ms: u32,
+    since: Option<u32>,
+    reported: bool,
+}
+
+#[cfg(feature = "driver")]
+impl DisconnectMonitor {
+    /// create a new disconnect monitor
+    pub fn new(min_velocity: u32, max_velocity: u32, sg_no_load: u16, persist_ms: u32) -> Self {
+        DisconnectMonitor {
+            min_velocity,
+            max_velocity,
+            sg_no_load,
+            persist_ms,
+            since: None,
+            reported: false,
+        }
+    }
+
+    /// sample DRV_STATUS and VACTUAL, returns an event when a disconnect is detected
+    ///
+    /// `now_ms` is a monotonic millisecond timestamp of the application. Outside of the velocity
+    /// window and in stealthChop the persistence timer is kept, so short excursions do not reset
+    /// the detection.
+    pub fn update<A, SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut A,
+        now_ms: u32,
+    ) -> Result<Option<DisconnectEvent>, Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        driver.access(|driver| self.update_driver(driver, now_ms))
+    }
+
+    fn update_driver<SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut Tmc5160<SPI, CS, EN>,
+        now_ms: u32,
+    ) -> Result<Option<DisconnectEvent>, Error<E>>
+    where
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        let status = driver.read_drv_status()?;
+        let vactual = driver.read_register(Registers::VACTUAL)?.data;
+        // sign extend the 24 bit value
+        let velocity = (((vactual << 8) as i32) >> 8).unsigned_abs();
+        // the TMC5130 has no DRV_STATUS.stealth flag, skip whenever stealthChop is enabled
+        let stealth = match CHIP {
+            Chip::Tmc5130 => driver.g_conf.en_pwm_mode(),
+            _ => status.stealth(),
+        };
+        if stealth || velocity < self.min_velocity || velocity > self.max_velocity {
+            return Ok(None);
+        }
+        let open = status.ola() || status.olb();
+        if !open || status.sg_result() < self.sg_no_load {
+            self.since = None;
+            self.reported = false;
+            return Ok(None);
+        }
+        let since = *self.since.get_or_insert(now_ms);
+        if self.reported || now_ms.wrapping_sub(since) < self.persist_ms {
+            return Ok(None);
+        }
+        self.reported = true;
+        Ok(Some(DisconnectEvent {
+            phase_a: status.ola(),
+            phase_b: status.olb(),
+            sg_result: status.sg_result(),
+        }))
+    }
+}
+
+/// Saturated stealthChop amplitude regulation, reported by `SaturationMonitor::update()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SaturationEvent {
+    /// PWM_SCALE_SUM at the time of the detection
+    pub pwm_scale_sum: u8,
+    /// signed PWM_SCALE_AUTO at the time of the detection
+    pub pwm_scale_auto: i16,
+    /// |VACTUAL| at the time of the detection (raw register value)
+    pub velocity: u32,
+}
+
+/// Detection of a saturated stealthChop amplitude regulation
+///
+/// When the supply voltage is too low or the velocity too high for stealthChop, PWM_SCALE_SUM
+/// stays at its maximum and the regulation cannot reach the target current any more, which
+/// silently leads to lost steps. A saturation is reported when PWM_SCALE_SUM stays at or above
+/// `threshold` for `persist_ms` in stealthChop, once until the condition clears. Lower TPWMTHRS to
+/// switch to spreadCycle below the affected velocity or raise the supply voltage.
+#[cfg(feature = "driver")]
+#[derive(Debug, Copy, Clone)]
+pub struct SaturationMonitor {
+    /// PWM_SCALE_SUM (0 to 255) considered saturated, usually 255
+    pub threshold: u8,
+    /// time in ms the saturation has to persist
+    pub persist_ms: u32,
+    since: Option<u32>,
+    reported: bool,
+}
+
+#[cfg(feature = "driver")]
+impl SaturationMonitor {
+    /// create a new saturation monitor
+    pub fn new(threshold: u8, persist_ms: u32) -> Self {
+        SaturationMonitor {
+            threshold,
+            persist_ms,
+            since: None,
+            reported: false,
+        }
+    }
+
+    /// sample DRV_STATUS and PWM_SCALE, returns an event when a saturation is detected
+    ///
+    /// `now_ms` is a monotonic millisecond timestamp of the application. VACTUAL is only read for
+    /// the event. Returns `Error::Unsupported` on the TMC5130, which has neither DRV_STATUS.stealth
+    /// nor PWM_SCALE_AUTO.
+    pub fn update<A, SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut A,
+        now_ms: u32,
+    ) -> Result<Option<SaturationEvent>, Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        driver.access(|driver| self.update_driver(driver, now_ms))
+    }
+
+    fn update_driver<SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut Tmc5160<SPI, CS, EN>,
+        now_ms: u32,
+    ) -> Result<Option<SaturationEvent>, Error<E>>
+    where
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        if CHIP == Chip::Tmc5130 {
+            return Err(Error::Unsupported);
+        }
+        let stealth = driver.read_drv_status()?.stealth();
+        let scale = driver.read_pwm_scale()?;
+        if !stealth || scale.pwm_scale_sum() < self.threshold {
+            self.since = None;
+            self.reported = false;
+            return Ok(None);
+        }
+        let since = *self.since.get_or_insert(now_ms);
+        if self.reported || now_ms.wrapping_sub(since) < self.persist_ms {
+            return Ok(None);
+        }
+        self.reported = true;
+        let vactual = driver.read_register(Registers::VACTUAL)?.data;
+        Ok(Some(SaturationEvent {
+            pwm_scale_sum: scale.pwm_scale_sum(),
+            pwm_scale_auto: scale.pwm_scale_auto_signed(),
+            // sign extend the 24 bit value
+            velocity: (((vactual << 8) as i32) >> 8).unsigned_abs(),
+        }))
+    }
+}
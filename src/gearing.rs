@@ -0,0 +1,110 @@
+//! Electronic gearing of an axis to a master position
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+
+use crate::split::DriverAccess;
+use crate::stream::TrajectoryStream;
+use crate::{Error, Tmc5160};
+
+/// Follows a master position with the ratio `numerator / denominator`
+///
+/// The master position can be any counter in steps, e.g. XACTUAL of another driver or an encoder
+/// count (`read_x_enc()`). Every `update()` moves the follower to
+/// `follower_origin + (master - master_origin) * numerator / denominator`, where the origins are
+/// the positions at `engage()`. The targets are streamed with `TrajectoryStream`, so the follower
+/// tracks the master smoothly, lagging by one update interval.
+#[derive(Debug, Copy, Clone)]
+pub struct Gearing {
+    /// ratio numerator, negative to follow in the opposite direction
+    pub numerator: i32,
+    /// ratio denominator
+    pub denominator: i32,
+    origin: Option<(i32, i32)>,
+    last_ms: u32,
+    stream: TrajectoryStream,
+}
+
+impl Gearing {
+    /// create a gearing with the ratio `numerator / denominator`
+    pub fn new(numerator: i32, denominator: i32) -> Self {
+        Gearing {
+            numerator,
+            denominator,
+            origin: None,
+            last_ms: 0,
+            stream: TrajectoryStream::new(1),
+        }
+    }
+
+    /// check if the follower is engaged
+    pub fn is_engaged(&self) -> bool {
+        self.origin.is_some()
+    }
+
+    /// couple the follower at its actual position to the master at `master_position`
+    pub fn engage<A, SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut A,
+        master_position: i32,
+        now_ms: u32,
+    ) -> Result<(), Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        if self.denominator == 0 {
+            return Err(Error::InvalidValue);
+        }
+        self.stream.start(driver)?;
+        let follower_position = self.stream.last_point().unwrap_or(0);
+        self.origin = Some((master_position, follower_position));
+        self.last_ms = now_ms;
+        Ok(())
+    }
+
+    /// track the master position, returns the new follower target
+    ///
+    /// `now_ms` is a monotonic millisecond timestamp of the application, call it periodically.
+    pub fn update<A, SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut A,
+        master_position: i32,
+        now_ms: u32,
+    ) -> Result<i32, Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        let (master_origin, follower_origin) = self.origin.ok_or(Error::NotConfigured)?;
+        let offset = (master_position as i64 - master_origin as i64) * self.numerator as i64
+            / self.denominator as i64;
+        let target =
+            (follower_origin as i64 + offset).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        let elapsed_ms = now_ms.wrapping_sub(self.last_ms);
+        if elapsed_ms == 0 {
+            return Ok(self.stream.last_point().unwrap_or(target));
+        }
+        self.stream.period_us = elapsed_ms.saturating_mul(1000);
+        self.stream.push(driver, target)?;
+        self.last_ms = now_ms;
+        Ok(target)
+    }
+
+    /// decouple the follower, it stops at the last target
+    pub fn disengage<A, SPI, CS, EN, E>(&mut self, driver: &mut A) -> Result<(), Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        self.origin = None;
+        self.stream.finish(driver)
+    }
+}
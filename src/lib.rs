@@ -4,6 +4,11 @@
 //!
 //! [`embedded-hal`]: https://docs.rs/embedded-hal/0.2
 //!
+//! The driver is enabled by the default `driver` feature. Without it, only the register model
+//! (`registers`, `chip`, `conversions`, `microstep`, `events` and the `decode` module with `std`)
+//! is built, without depending on embedded-hal, so host side configurators and other transports
+//! can reuse it.
+//!
 #![no_std]
 #![allow(dead_code)]
 #![deny(missing_docs)]
@@ -12,15 +17,67 @@
 use core::fmt;
 use core::result::Result;
 
+#[cfg(feature = "driver")]
+use embedded_hal::spi::{Mode, Phase, Polarity};
+#[cfg(feature = "driver")]
 use embedded_hal::{
+    blocking::delay::DelayMs,
     blocking::spi::{Transfer, Write},
     digital::v2::OutputPin,
-    spi::{Mode, Phase, Polarity},
 };
 
+#[cfg(feature = "driver")]
+use crate::chip::{Chip, CHIP};
+#[cfg(feature = "driver")]
+use crate::deadline::Deadline;
+#[cfg(feature = "embassy")]
+use crate::deadline::TimerDeadline;
+#[cfg(feature = "driver")]
+use crate::events::{status_mask, StatusEvents};
+use crate::fault::FaultFlags;
+#[cfg(feature = "driver")]
+use crate::microstep::{ElectricalState, MicrostepTable};
+#[cfg(feature = "driver")]
+use crate::ramp::RampProfile;
 use crate::registers::*;
-
+#[cfg(feature = "driver")]
+use crate::split::{CachedStatus, StatusCache};
+
+#[cfg(feature = "driver")]
+pub mod bitbang;
+pub mod chip;
+pub mod conversions;
+#[cfg(feature = "driver")]
+pub mod correction;
+#[cfg(feature = "driver")]
+pub mod deadline;
+#[cfg(feature = "std")]
+pub mod decode;
+#[cfg(all(feature = "critical-section", feature = "driver"))]
+pub mod event_stream;
+pub mod events;
+pub mod fault;
+#[cfg(feature = "driver")]
+pub mod gearing;
+#[cfg(feature = "driver")]
+pub mod load;
+pub mod microstep;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(all(feature = "stepper", feature = "driver"))]
+pub mod motion_control;
+pub mod ramp;
 pub mod registers;
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(feature = "driver")]
+pub mod slim;
+#[cfg(feature = "driver")]
+pub mod split;
+#[cfg(feature = "driver")]
+pub mod stream;
+#[cfg(feature = "driver")]
+pub mod uart;
 
 fn swap_bytes(input: [u8; 4]) -> [u8; 4] {
     let mut output = [0; 4];
@@ -30,7 +87,64 @@ fn swap_bytes(input: [u8; 4]) -> [u8; 4] {
     output
 }
 
+/// convert a non-negative value to a register value of at most `max`, `None` if out of range
+fn checked_register(value: f32, max: u32) -> Option<u32> {
+    if !value.is_finite() || value < 0.0 || value >= max as f32 + 1.0 {
+        return None;
+    }
+    Some(value as u32)
+}
+
+/// check a raw register value against the datasheet limits
+fn checked_range<E>(val: u32, min: u32, max: u32) -> Result<u32, Error<E>> {
+    if val < min || val > max {
+        return Err(Error::InvalidValue);
+    }
+    Ok(val)
+}
+
+/// VMAX register value for the nominal `v_max` scaled to `percent`
+fn scaled_v_max<E>(v_max: u32, percent: u16) -> Result<u32, Error<E>> {
+    let scaled = (v_max as u64 * percent as u64 / 100).min(u32::MAX as u64) as u32;
+    checked_range(scaled, 0, VMAX_LIMIT)
+}
+
+fn toff_clocks(toff: u8) -> u32 {
+    24 + 32 * toff as u32
+}
+
+fn tbl_clocks(tbl: u8) -> u32 {
+    match tbl {
+        0 => 16,
+        1 => 24,
+        2 => 36,
+        _ => 54,
+    }
+}
+
+/// maximum VMAX value
+const VMAX_LIMIT: u32 = (1 << 23) - 512;
+
+/// Nominal frequency of the internal clock with the factory trim value
+pub const INTERNAL_CLOCK: f32 = 12_000_000.0;
+
+/// Lowest supported frequency of an external clock in Hz
+pub const EXTERNAL_CLOCK_MIN: u32 = 4_000_000;
+
+/// Highest supported frequency of an external clock in Hz
+pub const EXTERNAL_CLOCK_MAX: u32 = 18_000_000;
+
+/// Clock source of the chip
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ClockSource {
+    /// internal oscillator (CLK pin tied to GND), nominally `INTERNAL_CLOCK`
+    Internal,
+    /// external clock on the CLK pin with the frequency in Hz
+    External(u32),
+}
+
 /// SPI mode
+#[cfg(feature = "driver")]
 pub const MODE: Mode = Mode {
     phase: Phase::CaptureOnSecondTransition,
     polarity: Polarity::IdleHigh,
@@ -43,9 +157,60 @@ pub enum Error<E> {
     Spi(E),
     /// Pin error
     PinError,
+    /// the motor has to be at standstill for this operation
+    NotAtStandstill,
+    /// the operation did not complete within the timeout
+    Timeout,
+    /// value is outside of the range allowed by the datasheet
+    InvalidValue,
+    /// charge pump undervoltage (GSTAT.uv_cp), the driver is disabled
+    ChargePumpUndervoltage,
+    /// formatting error of the output
+    Format,
+    /// the SPI status reports a driver error or a reset (only in strict status mode)
+    Status(SpiStatus),
+    /// the SD_MODE pin strapping (IOIN.sd_mode) does not allow the requested mode
+    SdMode,
+    /// the operation requires a mode that is not enabled in the configuration
+    NotConfigured,
+    /// the value read back from a register differs from the written value
+    ReadbackMismatch(Mismatch),
+    /// the driver reports a fault in DRV_STATUS or GSTAT
+    DriverFault(FaultFlags),
+    /// the register or feature is not available on the selected chip variant
+    Unsupported,
+    /// an emergency stop is latched, motion commands are rejected until it is cleared
+    EmergencyStop,
+    /// the target is outside of the soft limits, or a velocity mode was selected with soft limits
+    SoftLimit,
+    /// a split-phase transfer was started while another one is pending, or finished without start
+    TransferSequence,
+    /// the write sets reserved bits (address, offending bits), only with `ReservedBits::Reject`
+    ReservedBits(u8, u32),
+}
+
+/// Handling of writes that set reserved bits, see `Chip::valid_bits()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReservedBits {
+    /// write the value unchanged (default)
+    Allow,
+    /// clear the reserved bits before writing
+    Mask,
+    /// reject the write with `Error::ReservedBits`
+    Reject,
+}
+
+/// Chopper mode of the driver
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChopperMode {
+    /// voltage PWM mode (en_pwm_mode = 1), quiet at low velocities
+    StealthChop,
+    /// cycle-by-cycle current control (en_pwm_mode = 0)
+    SpreadCycle,
 }
 
 /// Data Exchange packet
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub struct DataPacket {
     /// Status returned from last communication
     pub status: SpiStatus,
@@ -55,13 +220,394 @@ pub struct DataPacket {
     pub debug: [u8; 5],
 }
 
+/// data word followed by the names of the set status flags, e.g. `0x00000200 standstill`
 impl fmt::Display for DataPacket {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "0x{:x}:0x{:x}", self.status.into_bytes()[0], self.data)
+        write!(f, "{:#010x}", self.data)?;
+        let status = &self.status;
+        let flags = [
+            (status.reset_flag(), "reset"),
+            (status.driver_error(), "driver_error"),
+            (status.sg2(), "sg2"),
+            (status.standstill(), "standstill"),
+            (status.velocity_reached(), "velocity_reached"),
+            (status.position_reached(), "position_reached"),
+            (status.status_stop_l(), "stop_l"),
+            (status.status_stop_r(), "stop_r"),
+        ];
+        for (_, name) in flags.iter().filter(|(set, _)| *set) {
+            write!(f, " {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Configuration of the automatic standstill current reduction
+#[derive(Debug, Copy, Clone)]
+pub struct StandstillReduction {
+    /// time at standstill in ms before the current is reduced
+    pub delay_ms: u32,
+    /// hold current (0 to 31) used after the delay
+    pub deep_hold: u8,
+}
+
+/// Background duties performed by `Tmc5160::tick()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct TickDuties {
+    /// sample DRV_STATUS, updating the event counters and the read cache
+    pub status: bool,
+    /// run the standstill current reduction (see `set_standstill_reduction()`)
+    pub standstill: bool,
+    /// check and repair GCONF and CHOPCONF (see `watchdog_tick()`)
+    pub watchdog: bool,
+    /// limit the run current to the given percent while DRV_STATUS reports an overtemperature
+    /// (pre-)warning, on top of `set_torque_limit_percent()`
+    pub thermal: Option<u8>,
+    /// poll for stall events (see `poll_stall()`), updating the event counters
+    pub counters: bool,
+    /// read RAMP_STAT and XACTUAL past the read cache, refreshing it and the status cache
+    pub cache: bool,
+}
+
+/// Duty performed by a call of `Tmc5160::tick()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TickDuty {
+    /// DRV_STATUS has been sampled
+    Status(DrvStatus),
+    /// the standstill current reduction ran, true while the deep hold current is active
+    Standstill(bool),
+    /// the watchdog ran, true if a register has been repaired
+    Watchdog(bool),
+    /// the thermal policy ran, true while the run current is limited
+    Thermal(bool),
+    /// the event counters have been updated
+    Counters(Counters),
+    /// RAMP_STAT and XACTUAL have been read again
+    Cache,
+}
+
+/// Standstill current of a parked motor, see `Tmc5160::park()`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ParkHold {
+    /// hold current (0 to 31)
+    Current(u8),
+    /// IHOLD = 0 with a standstill option of PWMCONF, only effective with stealthChop
+    FreeWheel(FreeWheel),
+}
+
+/// Kind of a detected stall
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StallKind {
+    /// the motor was stopped by stallGuard (sg_stop, RAMP_STAT.event_stop_sg is latched)
+    Stopped,
+    /// SG_RESULT crossed the stallGuard threshold (DRV_STATUS.stallguard) without stopping the motor
+    Threshold,
+}
+
+/// Stall event reported by `poll_stall()`
+#[derive(Debug, Copy, Clone)]
+pub struct StallEvent {
+    /// kind of the stall
+    pub kind: StallKind,
+    /// SG_RESULT at detection
+    pub sg_result: u16,
+}
+
+/// Motion state saved by `emergency_stop()` for `recover_from_emergency_stop()`
+#[derive(Debug, Copy, Clone)]
+pub struct EmergencyStopState {
+    /// ramp mode before the stop
+    pub ramp_mode: RampMode,
+    /// XTARGET before the stop in microsteps
+    pub target: i32,
+    /// max velocity before the stop
+    pub v_max: f32,
+}
+
+/// Raw value of a ramp register and the value in physical units
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RampValue {
+    /// register value
+    pub raw: u32,
+    /// steps/s for velocities, steps/s² for accelerations and s for TZEROWAIT
+    pub physical: f32,
+}
+
+/// Ramp generator parameters returned by `read_ramp_snapshot()`
+///
+/// Apart from RAMPMODE, the ramp registers are write-only. Their values are the ones last sent to
+/// the chip by any write, including `write_register()`, and `None` if the register was not written
+/// through this driver.
+#[derive(Debug, Copy, Clone)]
+pub struct RampSnapshot {
+    /// ramp mode read from the chip
+    pub ramp_mode: RampMode,
+    /// VSTART
+    pub vstart: Option<RampValue>,
+    /// A1
+    pub a1: Option<RampValue>,
+    /// V1
+    pub v1: Option<RampValue>,
+    /// AMAX
+    pub amax: Option<RampValue>,
+    /// VMAX
+    pub vmax: Option<RampValue>,
+    /// DMAX
+    pub dmax: Option<RampValue>,
+    /// D1
+    pub d1: Option<RampValue>,
+    /// VSTOP
+    pub vstop: Option<RampValue>,
+    /// TZEROWAIT
+    pub tzerowait: Option<RampValue>,
+}
+
+/// write-only ramp registers tracked for `read_ramp_snapshot()`
+const RAMP_REGISTERS: [Registers; 9] = [
+    Registers::VSTART,
+    Registers::A1,
+    Registers::V1,
+    Registers::AMAX,
+    Registers::VMAX,
+    Registers::DMAX,
+    Registers::D1,
+    Registers::VSTOP,
+    Registers::TZEROWAIT,
+];
+
+/// RMS motor current in mA, see `Tmc5160::set_rms_current()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RmsCurrent(u16);
+
+impl RmsCurrent {
+    /// current in mA, at least 1 mA
+    pub fn from_ma(ma: u16) -> Option<Self> {
+        (ma > 0).then_some(RmsCurrent(ma))
+    }
+
+    /// current in mA
+    pub fn ma(self) -> u16 {
+        self.0
+    }
+
+    /// current in A
+    pub fn amps(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+/// Hold current as percentage of the run current, see `Tmc5160::set_rms_current()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HoldPercent(u8);
+
+impl HoldPercent {
+    /// percentage from 0 to 100
+    pub fn new(percent: u8) -> Option<Self> {
+        (percent <= 100).then_some(HoldPercent(percent))
+    }
+
+    /// percentage from 0 to 100
+    pub fn percent(self) -> u8 {
+        self.0
+    }
+}
+
+/// Speed bands of the application, see `Tmc5160::set_speed_profile()`
+///
+/// Velocities are in the unit of `set_velocity()`, ordered `quiet <= working < rapid`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpeedProfile {
+    /// highest velocity running in stealthChop
+    pub quiet: f32,
+    /// lowest velocity with coolStep and stallGuard
+    pub working: f32,
+    /// velocity above which the high velocity mode is active and coolStep and stallGuard are off
+    pub rapid: f32,
+}
+
+/// TSTEP thresholds planned from a `SpeedProfile`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VelocityThresholds {
+    /// TPWMTHRS, stealthChop below the quiet velocity
+    pub tpwmthrs: u32,
+    /// TCOOLTHRS, coolStep and stallGuard from the working velocity
+    pub tcoolthrs: u32,
+    /// THIGH, high velocity mode above the rapid velocity
+    pub thigh: u32,
+}
+
+/// Configuration of the current ramp on enable, see `Tmc5160::set_enable_ramp()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EnableRamp {
+    /// run current (0 to 31) at the start of the ramp
+    pub start: u8,
+    /// duration of the ramp in ms
+    pub duration_ms: u32,
+    /// number of current steps (at least 1)
+    pub steps: u8,
+}
+
+/// Configuration of the read cache, see `Tmc5160::set_read_cache()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReadCache {
+    /// max age of a cached value in ms, older values are read again
+    pub max_age_ms: u32,
+}
+
+/// frequently polled registers kept by the read cache
+const CACHED_REGISTERS: [Registers; 3] = [
+    Registers::DRV_STATUS,
+    Registers::RAMP_STAT,
+    Registers::XACTUAL,
+];
+
+/// Register whose value on the chip differs from the shadow register
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// register
+    pub register: Registers,
+    /// value of the shadow register
+    pub expected: u32,
+    /// value read from the chip
+    pub actual: u32,
+}
+
+/// Mismatches found by `verify_config()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Mismatches {
+    items: [Option<Mismatch>; 4],
+}
+
+impl Mismatches {
+    fn push(&mut self, mismatch: Mismatch) {
+        if let Some(slot) = self.items.iter_mut().find(|item| item.is_none()) {
+            *slot = Some(mismatch);
+        }
+    }
+
+    /// check if the configuration matches
+    pub fn is_empty(&self) -> bool {
+        self.items[0].is_none()
+    }
+
+    /// number of mismatching registers
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// iterate over the mismatching registers
+    pub fn iter(&self) -> impl Iterator<Item = &Mismatch> {
+        self.items.iter().flatten()
+    }
+}
+
+/// Decoded fault flags of DRV_STATUS, GSTAT and RAMP_STAT
+///
+/// The `Display` implementation renders the set flags into a compact one-line report.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FaultReport {
+    /// DRV_STATUS register
+    pub drv_status: DrvStatus,
+    /// GSTAT register
+    pub gstat: GStat,
+    /// RAMP_STAT register
+    pub ramp_stat: RampStat,
+}
+
+impl FaultReport {
+    /// driver faults of the report, without the informational and ramp flags
+    pub fn flags(&self) -> FaultFlags {
+        FaultFlags::from_registers(self.drv_status, self.gstat)
+    }
+}
+
+impl fmt::Display for FaultReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = &self.drv_status;
+        let g = &self.gstat;
+        let r = &self.ramp_stat;
+        let flags = [
+            (g.reset(), "reset"),
+            (g.drv_err(), "drv_err"),
+            (g.uv_cp(), "uv_cp"),
+            (d.ot(), "ot"),
+            (d.otpw(), "otpw"),
+            (d.s2ga(), "s2ga"),
+            (d.s2gb(), "s2gb"),
+            (d.s2vsa(), "s2vsa"),
+            (d.s2vsb(), "s2vsb"),
+            (d.ola(), "ola"),
+            (d.olb(), "olb"),
+            (d.stallguard(), "stall"),
+            (r.event_stop_l(), "stop_l"),
+            (r.event_stop_r(), "stop_r"),
+            (r.event_stop_sg(), "stop_sg"),
+        ];
+        let mut any = false;
+        for (_, name) in flags.iter().filter(|(set, _)| *set) {
+            if any {
+                f.write_str(" ")?;
+            }
+            f.write_str(name)?;
+            any = true;
+        }
+        if !any {
+            f.write_str("no faults")?;
+        }
+        Ok(())
     }
 }
 
+/// Event counters of the driver, see `Tmc5160::get_counters()`
+///
+/// Events are counted once per occurrence when the driver observes them, the counters wrap around.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Counters {
+    /// stall events reported by `poll_stall()`
+    pub stall_events: u32,
+    /// short circuits seen in DRV_STATUS (s2ga, s2gb, s2vsa, s2vsb)
+    pub short_events: u32,
+    /// overtemperature pre-warnings seen in DRV_STATUS (otpw)
+    pub thermal_warnings: u32,
+    /// resets seen in the SPI status (reset_flag)
+    pub resets: u32,
+    /// failed SPI transfers, each one has to be retried by the application
+    pub spi_errors: u32,
+}
+
+/// Result of `Tmc5160::detect_motor()`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MotorDetection {
+    /// phase A carries current (DRV_STATUS.ola not set)
+    pub phase_a: bool,
+    /// phase B carries current (DRV_STATUS.olb not set)
+    pub phase_b: bool,
+}
+
+impl MotorDetection {
+    /// check if a motor is connected on both phases
+    pub fn is_connected(&self) -> bool {
+        self.phase_a && self.phase_b
+    }
+}
+
+/// Result of `Tmc5160::check_tstep()`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TstepCheck {
+    /// measured TSTEP value
+    pub tstep: u32,
+    /// VACTUAL value at the time of the measurement
+    pub vactual: i32,
+    /// TSTEP value implied by VACTUAL, `None` if it is beyond the 20 bit range of TSTEP
+    pub expected: Option<f32>,
+    /// relative deviation of TSTEP from the expected value, `None` if it cannot be compared
+    pub deviation: Option<f32>,
+    /// the deviation exceeds the tolerance
+    pub disagrees: bool,
+}
+
 /// TMC5160 driver
+#[cfg(feature = "driver")]
 pub struct Tmc5160<SPI, CS, EN> {
     spi: SPI,
     cs: CS,
@@ -73,8 +619,53 @@ pub struct Tmc5160<SPI, CS, EN> {
     /// debug info of the last transmission
     pub debug: [u8; 5],
     _clock: f32,
+    _clock_source: ClockSource,
     _step_count: f32,
     _en_inverted: bool,
+    _strict_status: bool,
+    _reserved_bits: ReservedBits,
+    _counters: Counters,
+    _reset_seen: bool,
+    _short_seen: bool,
+    _otpw_seen: bool,
+    _tick_duties: TickDuties,
+    _tick_slot: u8,
+    _x_enc_sample: Option<i32>,
+    _microstep_table: MicrostepTable,
+    _enable_ramp: Option<EnableRamp>,
+    _enabled: bool,
+    _standstill_reduction: Option<StandstillReduction>,
+    _standstill_since: Option<u32>,
+    _deep_hold_active: bool,
+    _torque_limit: u8,
+    _thermal_limit: u8,
+    _global_scaler: u8,
+    _r_sense: Option<f32>,
+    _stall_callback: Option<fn(StallEvent)>,
+    _stall_stopped: bool,
+    _stall_threshold: bool,
+    _status_mask: u32,
+    _move_armed: bool,
+    _move_at_target: bool,
+    _deceleration_set: bool,
+    _emergency_stop: bool,
+    _emergency_stop_state: Option<EmergencyStopState>,
+    _a_max: Option<u32>,
+    _backlash: i32,
+    _backlash_offset: i32,
+    _backlash_positive: Option<bool>,
+    _backlash_target: Option<i32>,
+    _soft_limits: Option<(i32, i32)>,
+    _ramp_written: [Option<u32>; 9],
+    _move_defaults: Option<(Option<u32>, Option<u32>)>,
+    _park_free_wheel: bool,
+    _v_max_nominal: Option<u32>,
+    _speed_override: u16,
+    _read_cache: Option<ReadCache>,
+    _now_ms: u32,
+    _cached: [Option<(u32, SpiStatus, u32)>; 3],
+    _split_pending: Option<u8>,
+    _status_cache: Option<&'static StatusCache>,
     /// value of the GCONF register
     pub g_conf: GConf,
     /// value of the NODECONF register
@@ -93,6 +684,10 @@ pub struct Tmc5160<SPI, CS, EN> {
     pub enc_mode: EncMode,
     /// value of the MSLUTSEL register
     pub ms_lut_sel: MsLutSel,
+    /// value of the MSLUTSTART register
+    pub ms_lut_start: MsLutStart,
+    /// value of the DCCTRL register
+    pub dc_ctrl: DcCtrl,
     /// value of the CHOPCONF register
     pub chop_conf: ChopConf,
     /// value of the COOLCONF register
@@ -101,6 +696,7 @@ pub struct Tmc5160<SPI, CS, EN> {
     pub pwm_conf: PwmConf,
 }
 
+#[cfg(feature = "driver")]
 impl<SPI, CS, EN, E> Tmc5160<SPI, CS, EN>
 where
     SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
@@ -116,9 +712,54 @@ where
             v_max: 0.0,
             status: SpiStatus::new(),
             debug: [0; 5],
-            _clock: 12000000.0,
+            _clock: INTERNAL_CLOCK,
+            _clock_source: ClockSource::Internal,
             _step_count: 256.0,
             _en_inverted: false,
+            _strict_status: false,
+            _reserved_bits: ReservedBits::Allow,
+            _counters: Counters::default(),
+            _reset_seen: false,
+            _short_seen: false,
+            _otpw_seen: false,
+            _tick_duties: TickDuties::default(),
+            _tick_slot: 0,
+            _x_enc_sample: None,
+            _microstep_table: MicrostepTable::default(),
+            _enable_ramp: None,
+            _enabled: false,
+            _standstill_reduction: None,
+            _standstill_since: None,
+            _deep_hold_active: false,
+            _torque_limit: 100,
+            _thermal_limit: 100,
+            _global_scaler: 0,
+            _r_sense: None,
+            _stall_callback: None,
+            _stall_stopped: false,
+            _stall_threshold: false,
+            _status_mask: 0,
+            _move_armed: false,
+            _move_at_target: false,
+            _deceleration_set: false,
+            _emergency_stop: false,
+            _emergency_stop_state: None,
+            _a_max: None,
+            _backlash: 0,
+            _backlash_offset: 0,
+            _backlash_positive: None,
+            _backlash_target: None,
+            _soft_limits: None,
+            _ramp_written: [None; 9],
+            _move_defaults: None,
+            _park_free_wheel: false,
+            _v_max_nominal: None,
+            _speed_override: 100,
+            _read_cache: None,
+            _now_ms: 0,
+            _status_cache: None,
+            _cached: [None; 3],
+            _split_pending: None,
             g_conf: GConf::new(),
             node_conf: NodeConf::new(),
             otp_prog: OtpProg::new(),
@@ -128,6 +769,8 @@ where
             sw_mode: SwMode::new(),
             enc_mode: EncMode::new(),
             ms_lut_sel: MsLutSel::new(),
+            ms_lut_start: MsLutStart::new(),
+            dc_ctrl: DcCtrl::new(),
             chop_conf: ChopConf::new(),
             cool_conf: CoolConf::new(),
             pwm_conf: PwmConf::new(),
@@ -140,33 +783,133 @@ where
         self
     }
 
+    /// get a mutable reference to the SPI bus, e.g. to advance a `sim::Tmc5160Sim`
+    pub fn spi_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
     /// invert the enable pin
     pub fn en_inverted(mut self, inv: bool) -> Self {
         self._en_inverted = inv;
         self
     }
 
+    /// enable the strict status mode
+    ///
+    /// In strict status mode, every transfer whose SPI status reports `driver_error` or `reset_flag`
+    /// returns `Error::Status`. Accesses to GSTAT and DRV_STATUS are exempt, so the flags can still
+    /// be read and cleared.
+    pub fn strict_status(mut self, strict: bool) -> Self {
+        self._strict_status = strict;
+        self
+    }
+
+    /// select the handling of writes that set reserved bits
+    ///
+    /// With `ReservedBits::Mask` or `ReservedBits::Reject`, every write is checked against the
+    /// valid bits of the register on the selected chip, catching mistakes in a register value before
+    /// it reaches the hardware. Writes to read-only registers only pass with a value of 0.
+    pub fn reserved_bits(mut self, mode: ReservedBits) -> Self {
+        self._reserved_bits = mode;
+        self
+    }
+
+    fn check_reserved(&self, addr: u8, val: u32) -> Result<u32, Error<E>> {
+        let valid = CHIP.valid_bits(addr);
+        match self._reserved_bits {
+            ReservedBits::Allow => Ok(val),
+            ReservedBits::Mask => Ok(val & valid),
+            ReservedBits::Reject if val & !valid != 0 => {
+                Err(Error::ReservedBits(addr & 0x7F, val & !valid))
+            }
+            ReservedBits::Reject => Ok(val),
+        }
+    }
+
+    fn check_status(&mut self, addr: u8, status: SpiStatus) -> Result<(), Error<E>> {
+        if status.reset_flag() && !self._reset_seen {
+            self._counters.resets = self._counters.resets.wrapping_add(1);
+        }
+        self._reset_seen = status.reset_flag();
+        self.publish_status(|cached| cached.status = status);
+        let exempt = addr == Registers::GSTAT.addr() || addr == Registers::DRV_STATUS.addr();
+        if self._strict_status && !exempt && status.any(SpiStatus::ERROR_FLAGS) {
+            return Err(Error::Status(status));
+        }
+        Ok(())
+    }
+
     /// specify clock speed of the Tmc5160 (Default is 12 MHz)
+    ///
+    /// Overrides the frequency used for all time and velocity conversions without validation, e.g.
+    /// with a measured frequency of the internal clock. Use `clock_source()` to select the source.
     pub fn clock(mut self, clock: f32) -> Self {
         self._clock = clock;
         self
     }
 
+    /// specify the clock source of the Tmc5160 (Default is `ClockSource::Internal`)
+    ///
+    /// The frequency is used for all time and velocity conversions. An external clock has to be
+    /// within `EXTERNAL_CLOCK_MIN` and `EXTERNAL_CLOCK_MAX`.
+    pub fn clock_source(mut self, source: ClockSource) -> Result<Self, Error<E>> {
+        self._clock = match source {
+            ClockSource::Internal => INTERNAL_CLOCK,
+            ClockSource::External(hz) => {
+                checked_range(hz, EXTERNAL_CLOCK_MIN, EXTERNAL_CLOCK_MAX)? as f32
+            }
+        };
+        self._clock_source = source;
+        Ok(self)
+    }
+
+    /// get the clock source
+    pub fn get_clock_source(&self) -> ClockSource {
+        self._clock_source
+    }
+
+    /// get the clock frequency in Hz used for the conversions
+    pub fn get_clock(&self) -> f32 {
+        self._clock
+    }
+
     /// specify step count of the motor (Default is 256)
     pub fn step_count(mut self, step_count: f32) -> Self {
         self._step_count = step_count;
         self
     }
 
-    fn speed_from_hz(&mut self, speed_hz: f32) -> u32 {
-        return (speed_hz / (self._clock / 16_777_216.0) * self._step_count) as u32;
+    /// specify the sense resistor value in Ohm, required for the current calculations
+    pub fn sense_resistor(mut self, r_sense: f32) -> Self {
+        self._r_sense = Some(r_sense);
+        self
+    }
+
+    fn speed_from_hz(&mut self, speed_hz: f32) -> Result<u32, Error<E>> {
+        let v_max = conversions::velocity_to_register(speed_hz * self._step_count, self._clock);
+        checked_register(v_max, VMAX_LIMIT).ok_or(Error::InvalidValue)
+    }
+
+    fn tstep_from_hz(&mut self, speed_hz: f32) -> Result<u32, Error<E>> {
+        let v_max = self.speed_from_hz(speed_hz)?;
+        Ok(16_777_216_u32
+            .checked_div(v_max)
+            .map_or(0xFFFFF, |tstep| tstep.min(0xFFFFF)))
+    }
+
+    fn accel_from_hz(&mut self, accel_hz_per_s: f32) -> Result<u32, Error<E>> {
+        let a_max =
+            conversions::acceleration_to_register(accel_hz_per_s * self._step_count, self._clock);
+        checked_register(a_max, 0xFFFF).ok_or(Error::InvalidValue)
     }
 
-    fn accel_from_hz(&mut self, accel_hz_per_s: f32) -> u32 {
-        return (accel_hz_per_s / (self._clock * self._clock)
-            * (512.0 * 256.0)
-            * 16_777_216.0
-            * self._step_count) as u32;
+    fn steps_from_position(&self, position: f32) -> Result<i32, Error<E>> {
+        let steps = position * self._step_count;
+        // i32::MAX is not representable in f32, the next float is 2^31
+        if !steps.is_finite() || steps < i32::MIN as f32 || steps >= 2_147_483_648.0 {
+            return Err(Error::InvalidValue);
+        }
+        Ok(steps as i32)
     }
 
     /// read a specified register
@@ -174,10 +917,105 @@ where
     where
         T: Address + Copy,
     {
+        if !CHIP.has_register(reg.addr()) {
+            return Err(Error::Unsupported);
+        }
+        let cached = match self._read_cache {
+            Some(cache) => CACHED_REGISTERS
+                .iter()
+                .position(|r| r.addr() == reg.addr())
+                .map(|i| (i, self._now_ms, cache.max_age_ms)),
+            None => None,
+        };
+        if let Some((i, now_ms, max_age_ms)) = cached {
+            if let Some((time, status, data)) = self._cached[i] {
+                if now_ms.wrapping_sub(time) <= max_age_ms {
+                    let [a, b, c, d] = data.to_be_bytes();
+                    return Ok(DataPacket {
+                        status,
+                        data,
+                        debug: [status.into_bytes()[0], a, b, c, d],
+                    });
+                }
+            }
+        }
         // Process cmd to read, return previous (dummy) state
         let _dummy = self.read_io(reg)?;
         // Repeat cmd to read, return state
-        self.read_io(reg)
+        let packet = self.read_io(reg)?;
+        if let Some((i, now_ms, _)) = cached {
+            self._cached[i] = Some((now_ms, packet.status, packet.data));
+        }
+        match reg.addr() {
+            // DRV_STATUS, RAMP_STAT, XACTUAL
+            0x6F => self.count_drv_status(DrvStatus::from_bytes(packet.data.to_le_bytes())),
+            0x35 => self.publish_status(|cached| {
+                cached.ramp_stat = RampStat::from_bytes(packet.data.to_le_bytes())
+            }),
+            0x21 => self.publish_status(|cached| cached.xactual = packet.data as i32),
+            _ => {}
+        }
+        Ok(packet)
+    }
+
+    /// attach (`Some`) or detach (`None`) a cache publishing the status of every transaction
+    ///
+    /// The SPI status is updated with every transfer, RAMP_STAT and XACTUAL when they are read and
+    /// XACTUAL when it is written, see `StatusCache`.
+    pub fn set_status_cache(&mut self, cache: Option<&'static StatusCache>) {
+        self._status_cache = cache;
+    }
+
+    fn publish_status(&self, f: impl FnOnce(&mut CachedStatus)) {
+        if let Some(cache) = self._status_cache {
+            cache.update(f);
+        }
+    }
+
+    fn count_drv_status(&mut self, status: DrvStatus) {
+        let short = status.s2ga() || status.s2gb() || status.s2vsa() || status.s2vsb();
+        if short && !self._short_seen {
+            self._counters.short_events = self._counters.short_events.wrapping_add(1);
+        }
+        if status.otpw() && !self._otpw_seen {
+            self._counters.thermal_warnings = self._counters.thermal_warnings.wrapping_add(1);
+        }
+        self._short_seen = short;
+        self._otpw_seen = status.otpw();
+    }
+
+    /// event counters since the creation of the driver or the last `reset_counters()`
+    ///
+    /// Short circuits and thermal warnings are counted when DRV_STATUS is read, resets with every
+    /// transfer, so the application has to poll DRV_STATUS to keep them up to date.
+    pub fn get_counters(&self) -> Counters {
+        self._counters
+    }
+
+    /// clear the event counters
+    pub fn reset_counters(&mut self) {
+        self._counters = Counters::default();
+    }
+
+    /// enable (`Some`) or disable (`None`) caching of DRV_STATUS, RAMP_STAT and XACTUAL reads
+    ///
+    /// Reads of these registers within `max_age_ms` of the last transfer return the cached value,
+    /// so several subsystems can poll the status in the same loop without repeating the SPI
+    /// transfers. The age is measured with the timestamp of the last `set_time_ms()` or `tick()`.
+    /// Every write invalidates the cache.
+    pub fn set_read_cache(&mut self, cache: Option<ReadCache>) {
+        self._read_cache = cache;
+        self.invalidate_read_cache();
+    }
+
+    /// set the monotonic millisecond timestamp of the application used by the read cache
+    pub fn set_time_ms(&mut self, now_ms: u32) {
+        self._now_ms = now_ms;
+    }
+
+    /// drop all cached values, e.g. after accessing the chip through `spi_mut()`
+    pub fn invalidate_read_cache(&mut self) {
+        self._cached = [None; 3];
     }
 
     fn read_io<T>(&mut self, reg: T) -> Result<DataPacket, Error<E>>
@@ -188,7 +1026,10 @@ where
 
         let mut buffer = [reg.addr(), 0, 0, 0, 0];
 
-        let response = self.spi.transfer(&mut buffer).map_err(Error::Spi)?;
+        let response = self.spi.transfer(&mut buffer).map_err(|e| {
+            self._counters.spi_errors = self._counters.spi_errors.wrapping_add(1);
+            Error::Spi(e)
+        })?;
 
         self.cs.set_high().ok();
 
@@ -204,8 +1045,11 @@ where
             debug_val[i] = response[i];
         }
 
+        let status = SpiStatus::from_bytes([response[0]]);
+        self.check_status(reg.addr() & 0x7F, status)?;
+
         Ok(DataPacket {
-            status: SpiStatus::from_bytes([response[0]]),
+            status,
             data: u32::from_be_bytes(ret_val),
             debug: debug_val,
         })
@@ -216,29 +1060,63 @@ where
     where
         T: Address + Copy,
     {
+        if !CHIP.has_register(reg.addr()) {
+            return Err(Error::Unsupported);
+        }
+        *val = self
+            .check_reserved(reg.addr(), u32::from_be_bytes(*val))?
+            .to_be_bytes();
         self.cs.set_low().ok();
 
         let mut buffer = [reg.addr() | 0x80, val[0], val[1], val[2], val[3]];
 
         let debug_val = buffer.clone();
 
-        let response = self.spi.transfer(&mut buffer).map_err(Error::Spi)?;
+        let response = self.spi.transfer(&mut buffer).map_err(|e| {
+            self._counters.spi_errors = self._counters.spi_errors.wrapping_add(1);
+            Error::Spi(e)
+        })?;
 
         self.cs.set_high().ok();
 
+        self.track_write(reg.addr(), u32::from_be_bytes(*val));
+
         let mut ret_val: [u8; 4] = [0; 4];
 
         for i in 0..4 {
             ret_val[i] = response[i + 1];
         }
 
+        let status = SpiStatus::from_bytes([response[0]]);
+        self.check_status(reg.addr() & 0x7F, status)?;
+
         Ok(DataPacket {
-            status: SpiStatus::from_bytes([response[0]]),
+            status,
             data: u32::from_be_bytes(ret_val),
             debug: debug_val,
         })
     }
 
+    /// write a register and read it back, returning `Error::ReadbackMismatch` if they differ
+    ///
+    /// Only for read-write registers, write-only registers read back as 0.
+    pub fn write_register_verified(
+        &mut self,
+        reg: Registers,
+        val: u32,
+    ) -> Result<DataPacket, Error<E>> {
+        let packet = self.write_register(reg, &mut val.to_be_bytes())?;
+        let actual = self.read_register(reg)?.data;
+        if actual != val {
+            return Err(Error::ReadbackMismatch(Mismatch {
+                register: reg,
+                expected: val,
+                actual,
+            }));
+        }
+        Ok(packet)
+    }
+
     /// read a specified register according to the old implementation
     pub fn old_read_register(&mut self, register: u8, buffer: &mut [u8; 5]) {
         let mut read_cmd = [register, 0x00, 0x00, 0x00, 0x00];
@@ -305,21 +1183,169 @@ where
         status_byte
     }
 
+    /// bookkeeping of a register write
+    fn track_write(&mut self, addr: u8, val: u32) {
+        self.invalidate_read_cache();
+        if let Some(i) = RAMP_REGISTERS.iter().position(|r| r.addr() == addr) {
+            self._ramp_written[i] = Some(val);
+        }
+        let table = &mut self._microstep_table;
+        match addr {
+            0x60..=0x67 => table.mslut[(addr - 0x60) as usize] = val,
+            0x68 => table.sel = MsLutSel::from_bytes(val.to_le_bytes()),
+            0x69 => table.start = MsLutStart::from_bytes(val.to_le_bytes()),
+            _ => {}
+        }
+        match addr {
+            0x21 => self.publish_status(|cached| cached.xactual = val as i32),
+            0x35 => self.publish_status(|cached| {
+                let flags = u32::from_le_bytes(cached.ramp_stat.into_bytes()) & !val;
+                cached.ramp_stat = RampStat::from_bytes(flags.to_le_bytes())
+            }),
+            _ => {}
+        }
+    }
+
+    /// start a split-phase read of `reg` and return the datagram to transfer
+    ///
+    /// Pulls CS low. The caller transfers the datagram, e.g. by DMA, and passes the received bytes
+    /// to `finish_read()`, which releases CS. As with `read_register()`, the reply to a read
+    /// request arrives with the next datagram, so the value of `reg` is returned by the
+    /// `finish_read()` or `finish_write()` of the following transfer. No other register access may
+    /// be made while a split-phase transfer is pending.
+    pub fn start_read<T>(&mut self, reg: T) -> Result<[u8; 5], Error<E>>
+    where
+        T: Address + Copy,
+    {
+        self.start_transfer(reg.addr())?;
+        Ok([reg.addr(), 0, 0, 0, 0])
+    }
+
+    /// complete a split-phase read with the received bytes
+    ///
+    /// The data is the value of the register requested by the previous read datagram.
+    pub fn finish_read(&mut self, response: &[u8; 5]) -> Result<DataPacket, Error<E>> {
+        self.finish_transfer(response)
+    }
+
+    /// start a split-phase write of `val` to `reg` and return the datagram to transfer
+    ///
+    /// Pulls CS low, the transfer is completed with `finish_write()`.
+    pub fn start_write<T>(&mut self, reg: T, val: u32) -> Result<[u8; 5], Error<E>>
+    where
+        T: Address + Copy,
+    {
+        let val = self.check_reserved(reg.addr(), val)?;
+        self.start_transfer(reg.addr())?;
+        self.track_write(reg.addr(), val);
+        let [a, b, c, d] = val.to_be_bytes();
+        Ok([reg.addr() | 0x80, a, b, c, d])
+    }
+
+    /// complete a split-phase write with the received bytes
+    ///
+    /// The data is the value of the register requested by the previous read datagram.
+    pub fn finish_write(&mut self, response: &[u8; 5]) -> Result<DataPacket, Error<E>> {
+        self.finish_transfer(response)
+    }
+
+    fn start_transfer(&mut self, addr: u8) -> Result<(), Error<E>> {
+        if !CHIP.has_register(addr) {
+            return Err(Error::Unsupported);
+        }
+        if self._split_pending.is_some() {
+            return Err(Error::TransferSequence);
+        }
+        self._split_pending = Some(addr);
+        self.cs.set_low().ok();
+        Ok(())
+    }
+
+    fn finish_transfer(&mut self, response: &[u8; 5]) -> Result<DataPacket, Error<E>> {
+        let addr = self._split_pending.take().ok_or(Error::TransferSequence)?;
+        self.cs.set_high().ok();
+        let [status, a, b, c, d] = *response;
+        let status = SpiStatus::from_bytes([status]);
+        self.status = status;
+        self.check_status(addr, status)?;
+        Ok(DataPacket {
+            status,
+            data: u32::from_be_bytes([a, b, c, d]),
+            debug: *response,
+        })
+    }
+
     /// enable the motor if the EN pin was specified
+    ///
+    /// The motor is switched on with the full run current, see `enable_with_ramp()` for the
+    /// current ramp.
     pub fn enable(&mut self) -> Result<(), Error<E>> {
+        self.check_emergency_stop()?;
         if let Some(pin) = &mut self.en {
             if self._en_inverted {
-                pin.set_high().map_err(|_| Error::PinError)
+                pin.set_high().map_err(|_| Error::PinError)?;
             } else {
-                pin.set_low().map_err(|_| Error::PinError)
+                pin.set_low().map_err(|_| Error::PinError)?;
+            }
+        }
+        self._enabled = true;
+        Ok(())
+    }
+
+    /// enable the motor like `enable()`, ramping up the run current (see `set_enable_ramp()`)
+    ///
+    /// The ramp only runs when the motor was disabled before, the steps are waited with `delay`.
+    pub fn enable_with_ramp<D: DelayMs<u32>>(&mut self, delay: &mut D) -> Result<(), Error<E>> {
+        self.check_emergency_stop()?;
+        let ramp = self
+            ._enable_ramp
+            .filter(|_| self.en.is_some() && !self._enabled);
+        if let Some(ramp) = ramp {
+            self.write_ramped_current(ramp.start)?;
+        }
+        self.enable()?;
+        if let Some(ramp) = ramp {
+            let target = self.effective_ihold_irun().i_run();
+            let start = ramp.start.min(target);
+            for step in 1..=ramp.steps as u32 {
+                delay.delay_ms(ramp.duration_ms / ramp.steps as u32);
+                let i_run = start as u32 + (target - start) as u32 * step / ramp.steps as u32;
+                self.write_ramped_current(i_run as u8)?;
+            }
+            self._deep_hold_active = false;
+            self._standstill_since = None;
+        }
+        Ok(())
+    }
+
+    /// enable (`Some`) or disable (`None`) the current ramp of `enable_with_ramp()`
+    ///
+    /// When the motor is enabled through the EN pin, IRUN starts at `start` and rises to the
+    /// configured run current in `steps` steps over `duration_ms`, which avoids the clunk and the
+    /// supply dip of switching on the full current. `enable_with_ramp()` blocks for the duration
+    /// of the ramp.
+    pub fn set_enable_ramp(&mut self, ramp: Option<EnableRamp>) -> Result<(), Error<E>> {
+        if let Some(ramp) = ramp {
+            if ramp.start > 31 || ramp.steps == 0 {
+                return Err(Error::InvalidValue);
             }
-        } else {
-            Ok(())
         }
+        self._enable_ramp = ramp;
+        Ok(())
+    }
+
+    /// write IHOLD_IRUN with the run current limited to `i_run`, without changing the shadow
+    fn write_ramped_current(&mut self, i_run: u8) -> Result<DataPacket, Error<E>> {
+        let mut ihold_irun = self.effective_ihold_irun();
+        ihold_irun.set_i_run(ihold_irun.i_run().min(i_run));
+        ihold_irun.set_i_hold(ihold_irun.i_hold().min(i_run));
+        let mut value = swap_bytes(ihold_irun.into_bytes());
+        self.write_register(Registers::IHOLD_IRUN, &mut value)
     }
 
     /// disable the motor if the EN pin was specified
     pub fn disable(&mut self) -> Result<(), Error<E>> {
+        self._enabled = false;
         if let Some(pin) = &mut self.en {
             if self._en_inverted {
                 pin.set_low().map_err(|_| Error::PinError)
@@ -345,7 +1371,28 @@ where
         self.write_register(Registers::ENC_STATUS, &mut value)
     }
 
-    /// write value to SW_MODE register
+    /// acknowledge a single write-to-clear flag of the RAMP_STAT register
+    pub fn clear_ramp_event(&mut self, event: RampEvent) -> Result<DataPacket, Error<E>> {
+        self.clear_ramp_events(&[event])
+    }
+
+    /// acknowledge a set of write-to-clear flags of the RAMP_STAT register, other flags are not touched
+    pub fn clear_ramp_events(&mut self, events: &[RampEvent]) -> Result<DataPacket, Error<E>> {
+        let mut ramp_stat = RampStat::new();
+        for event in events {
+            event.set_in(&mut ramp_stat);
+        }
+        let mut value = swap_bytes(ramp_stat.into_bytes());
+        self.write_register(Registers::RAMP_STAT, &mut value)
+    }
+
+    /// acknowledge all write-to-clear flags of the RAMP_STAT register
+    pub fn clear_all_ramp_events(&mut self) -> Result<DataPacket, Error<E>> {
+        self.clear_ramp_events(&RampEvent::ALL)
+    }
+
+    /// write value to RAMP_STAT register
+    #[deprecated(note = "every set bit clears the flag, use `clear_ramp_events()` instead")]
     pub fn write_ramp_stat(&mut self, ramp_stat: RampStat) -> Result<DataPacket, Error<E>> {
         let mut value = swap_bytes(ramp_stat.into_bytes());
         self.write_register(Registers::RAMP_STAT, &mut value)
@@ -363,108 +1410,1261 @@ where
         self.write_register(Registers::GCONF, &mut value)
     }
 
-    /// write value to CHOP_CONF register
-    pub fn update_chop_conf(&mut self) -> Result<DataPacket, Error<E>> {
-        let mut value = swap_bytes(self.chop_conf.into_bytes());
-        self.write_register(Registers::CHOPCONF, &mut value)
+    /// invert the motor direction (GCONF.shaft) and write GCONF
+    pub fn set_shaft(&mut self, inverse: bool) -> Result<DataPacket, Error<E>> {
+        self.g_conf.set_shaft(inverse);
+        self.update_g_conf()
     }
 
-    /// write value to COOL_CONF register
-    pub fn update_cool_conf(&mut self) -> Result<DataPacket, Error<E>> {
-        let mut value = swap_bytes(self.cool_conf.into_bytes());
-        self.write_register(Registers::COOLCONF, &mut value)
+    /// invert the direction of motion
+    ///
+    /// Flips GCONF.shaft, so the ramp generator keeps counting XACTUAL, VACTUAL and XTARGET in the
+    /// commanded direction and `move_to()`, `set_velocity()` and `get_position()` stay consistent.
+    /// Call it before homing, as the physical axis is mirrored around the current position.
+    /// The encoder is not affected, use a negative ENC_CONST to invert its counting direction.
+    pub fn invert_direction(&mut self, invert: bool) -> Result<DataPacket, Error<E>> {
+        self.set_shaft(invert)
     }
 
-    /// write value to IHOLD_IRUN register
-    pub fn update_ihold_irun(&mut self) -> Result<DataPacket, Error<E>> {
-        let mut value = swap_bytes(self.ihold_irun.into_bytes());
-        self.write_register(Registers::IHOLD_IRUN, &mut value)
+    /// check if the direction of motion is inverted (GCONF.shaft)
+    pub fn direction_is_inverted(&self) -> bool {
+        self.g_conf.shaft()
     }
 
-    /// write value to PWM_CONF register
-    pub fn update_pwm_conf(&mut self) -> Result<DataPacket, Error<E>> {
-        let mut value = swap_bytes(self.pwm_conf.into_bytes());
-        self.write_register(Registers::PWMCONF, &mut value)
+    /// enable stealthChop (GCONF.en_pwm_mode), only possible at standstill, see `set_chopper_mode()`
+    pub fn enable_stealthchop(&mut self, enable: bool) -> Result<DataPacket, Error<E>> {
+        self.set_chopper_mode(if enable {
+            ChopperMode::StealthChop
+        } else {
+            ChopperMode::SpreadCycle
+        })
     }
 
-    /// write value to ENC_MODE register
-    pub fn update_enc_mode(&mut self) -> Result<DataPacket, Error<E>> {
-        let mut value = swap_bytes(self.enc_mode.into_bytes());
-        self.write_register(Registers::ENCMODE, &mut value)
+    /// enable step input filtering for stealthChop optimization (GCONF.multistep_filt) and write GCONF
+    ///
+    /// Returns `Error::Unsupported` on the TMC5130, where bit 3 is enc_commutation.
+    pub fn set_multistep_filt(&mut self, enable: bool) -> Result<DataPacket, Error<E>> {
+        if CHIP == Chip::Tmc5130 {
+            return Err(Error::Unsupported);
+        }
+        self.g_conf.set_multistep_filt(enable);
+        self.update_g_conf()
     }
 
-    /// write value to GLOBALSCALER register
-    pub fn set_global_scaler(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
-        self.write_register(Registers::GLOBALSCALER, &mut value)
+    /// enable faster standstill detection (GCONF.faststandstill) and write GCONF
+    ///
+    /// Returns `Error::Unsupported` on the TMC5130, where bit 1 is internal_Rsense.
+    pub fn set_faststandstill(&mut self, enable: bool) -> Result<DataPacket, Error<E>> {
+        if CHIP == Chip::Tmc5130 {
+            return Err(Error::Unsupported);
+        }
+        self.g_conf.set_faststandstill(enable);
+        self.update_g_conf()
     }
 
-    /// write value to TPOWERDOWN register
-    pub fn set_tpowerdown(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
-        self.write_register(Registers::TPOWERDOWN, &mut value)
+    /// enable motor stop by the DCEN input (GCONF.stop_enable) and write GCONF
+    pub fn set_stop_enable(&mut self, enable: bool) -> Result<DataPacket, Error<E>> {
+        self.g_conf.set_stop_enable(enable);
+        self.update_g_conf()
     }
 
-    /// write value to TPWMTHRS register
-    pub fn set_tpwmthrs(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
-        self.write_register(Registers::TPWMTHRS, &mut value)
+    /// enable zero crossing recalibration (GCONF.recalibrate) and write GCONF
+    ///
+    /// The recalibration takes place the next time the driver is disabled via ENN or TOFF. Returns
+    /// `Error::Unsupported` on the TMC5130, where bit 0 is I_scale_analog.
+    pub fn recalibrate(&mut self) -> Result<DataPacket, Error<E>> {
+        if CHIP == Chip::Tmc5130 {
+            return Err(Error::Unsupported);
+        }
+        self.g_conf.set_recalibrate(true);
+        self.update_g_conf()
     }
 
-    /// write value to TCOOLTHRS register
-    pub fn set_tcoolthrs(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
-        self.write_register(Registers::TCOOLTHRS, &mut value)
+    /// enable or disable direct coil current control (GCONF.direct_mode)
+    ///
+    /// In direct mode the ramp generator and the sine table are bypassed and XTARGET holds the
+    /// coil currents written by `set_coil_currents()`.
+    pub fn set_direct_mode(&mut self, enable: bool) -> Result<DataPacket, Error<E>> {
+        self.g_conf.set_direct_mode(enable);
+        self.update_g_conf()
     }
 
-    /// write value to A1 register
-    pub fn set_a1(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+    /// write the coil currents in direct mode (-255 to 255, scaled by IRUN and GLOBALSCALER)
+    pub fn set_coil_currents(&mut self, coil_a: i16, coil_b: i16) -> Result<DataPacket, Error<E>> {
+        self.check_emergency_stop()?;
+        if !self.g_conf.direct_mode() {
+            // XTARGET would be taken as a position
+            return Err(Error::NotConfigured);
+        }
+        let xdirect = XDirect::from_signed(coil_a, coil_b).ok_or(Error::InvalidValue)?;
+        let packet =
+            self.write_register(Registers::XTARGET, &mut swap_bytes(xdirect.into_bytes()))?;
+        self.status = packet.status;
+        Ok(packet)
+    }
+
+    /// check if a motor is connected by energizing both coils with the run current `i_run` (0 to 31)
+    ///
+    /// The open load flags are only valid in spreadCycle with current flowing in the coil, so the
+    /// coils are driven in direct mode with spreadCycle at 45° electrical (both currents at 71 %)
+    /// for 50 ms, waited with `delay`. Afterwards GCONF, IHOLD_IRUN and the EN pin are restored,
+    /// the motor does not move. Requires standstill and a TOFF setting in CHOPCONF.
+    pub fn detect_motor<D: DelayMs<u32>>(
+        &mut self,
+        i_run: u8,
+        delay: &mut D,
+    ) -> Result<MotorDetection, Error<E>> {
+        self.check_emergency_stop()?;
+        if i_run > 31 {
+            return Err(Error::InvalidValue);
+        }
+        if self.chop_conf.toff() == 0 {
+            return Err(Error::NotConfigured);
+        }
+        if !self.read_drv_status()?.standstill() {
+            return Err(Error::NotAtStandstill);
+        }
+        let was_enabled = self._enabled;
+        let xactual = self.read_register(Registers::XACTUAL)?.data;
+        let mut value = swap_bytes(
+            self.g_conf
+                .with_direct_mode(true)
+                .with_en_pwm_mode(false)
+                .into_bytes(),
+        );
+        self.write_register(Registers::GCONF, &mut value)?;
+        let current = IHoldIRun::new().with_i_run(i_run).with_i_hold(i_run);
+        let mut value = swap_bytes(current.into_bytes());
+        self.write_register(Registers::IHOLD_IRUN, &mut value)?;
+        let xdirect = XDirect::from_signed(181, 181).ok_or(Error::InvalidValue)?;
+        self.write_register(Registers::XTARGET, &mut swap_bytes(xdirect.into_bytes()))?;
+        self.enable()?;
+        delay.delay_ms(50);
+        let status = self.read_drv_status()?;
+        // XTARGET has to hold the position again before leaving direct mode
+        self.write_register(Registers::XTARGET, &mut xactual.to_be_bytes())?;
+        self.update_g_conf()?;
+        let mut value = swap_bytes(self.effective_ihold_irun().into_bytes());
+        self.write_register(Registers::IHOLD_IRUN, &mut value)?;
+        self._deep_hold_active = false;
+        if !was_enabled {
+            self.disable()?;
+        }
+        Ok(MotorDetection {
+            phase_a: !status.ola(),
+            phase_b: !status.olb(),
+        })
+    }
+
+    /// switch between stealthChop and spreadCycle
+    ///
+    /// Toggling en_pwm_mode while the motor is moving causes jerks, so the mode is only changed
+    /// when DRV_STATUS reports standstill, otherwise `Error::NotAtStandstill` is returned.
+    /// For velocity based switching while moving, use TPWMTHRS instead.
+    pub fn set_chopper_mode(&mut self, mode: ChopperMode) -> Result<DataPacket, Error<E>> {
+        let en_pwm_mode = mode == ChopperMode::StealthChop;
+        if self.g_conf.en_pwm_mode() != en_pwm_mode && !self.read_drv_status()?.standstill() {
+            return Err(Error::NotAtStandstill);
+        }
+        self.g_conf.set_en_pwm_mode(en_pwm_mode);
+        self.update_g_conf()
+    }
+
+    /// enable the high velocity mode above the specified velocity
+    ///
+    /// Sets THIGH to the TSTEP value of `velocity` and selects what happens above it:
+    /// `fullstep` switches to fullstep operation (vhighfs), `constant_off_time` switches the chopper
+    /// to constant off time mode (vhighchm).
+    pub fn enable_high_velocity_mode(
+        &mut self,
+        velocity: f32,
+        fullstep: bool,
+        constant_off_time: bool,
+    ) -> Result<DataPacket, Error<E>> {
+        let thigh = self.tstep_from_hz(velocity)?;
+        self.chop_conf.set_vhighfs(fullstep);
+        self.chop_conf.set_vhighchm(constant_off_time);
+        self.update_chop_conf()?;
+        self.set_thigh(thigh)
+    }
+
+    /// disable the high velocity mode (clears vhighfs, vhighchm and THIGH)
+    pub fn disable_high_velocity_mode(&mut self) -> Result<DataPacket, Error<E>> {
+        self.chop_conf.set_vhighfs(false);
+        self.chop_conf.set_vhighchm(false);
+        self.update_chop_conf()?;
+        self.set_thigh(0)
+    }
+
+    /// compute TPWMTHRS, TCOOLTHRS and THIGH for the speed bands of `profile`
+    ///
+    /// Returns `Error::InvalidValue` if the velocities are not ordered or the working and rapid
+    /// velocity map to the same TSTEP.
+    pub fn plan_velocity_thresholds(
+        &mut self,
+        profile: SpeedProfile,
+    ) -> Result<VelocityThresholds, Error<E>> {
+        let SpeedProfile {
+            quiet,
+            working,
+            rapid,
+        } = profile;
+        if !(quiet >= 0.0 && quiet <= working && working < rapid) {
+            return Err(Error::InvalidValue);
+        }
+        let thresholds = VelocityThresholds {
+            tpwmthrs: self.tstep_from_hz(quiet)?,
+            tcoolthrs: self.tstep_from_hz(working)?,
+            thigh: self.tstep_from_hz(rapid)?,
+        };
+        if thresholds.tcoolthrs <= thresholds.thigh {
+            return Err(Error::InvalidValue);
+        }
+        Ok(thresholds)
+    }
+
+    /// plan the velocity thresholds of `profile` and write TPWMTHRS, TCOOLTHRS and THIGH
+    ///
+    /// stealthChop (with en_pwm_mode) runs up to the quiet velocity, coolStep and stallGuard
+    /// between the working and the rapid velocity and the high velocity mode selected in CHOPCONF
+    /// (see `enable_high_velocity_mode()`) above the rapid velocity. Nothing is written if the
+    /// profile is invalid.
+    pub fn set_speed_profile(
+        &mut self,
+        profile: SpeedProfile,
+    ) -> Result<VelocityThresholds, Error<E>> {
+        let thresholds = self.plan_velocity_thresholds(profile)?;
+        self.set_tpwmthrs(thresholds.tpwmthrs)?;
+        self.set_tcoolthrs(thresholds.tcoolthrs)?;
+        self.set_thigh(thresholds.thigh)?;
+        Ok(thresholds)
+    }
+
+    /// set the slow decay time TOFF (0: driver disabled, 1 to 15) in the CHOP_CONF shadow register
+    ///
+    /// TOFF = 1 is only allowed with TBL >= 2. Call `update_chop_conf()` to write the register.
+    pub fn set_toff(&mut self, toff: u8) -> Result<(), Error<E>> {
+        if toff > 15 || (toff == 1 && self.chop_conf.tbl() < 2) {
+            return Err(Error::InvalidValue);
+        }
+        self.chop_conf.set_toff(toff);
+        Ok(())
+    }
+
+    /// set the comparator blank time TBL (0: 16, 1: 24, 2: 36, 3: 54 clocks) in the CHOP_CONF shadow register
+    ///
+    /// Call `update_chop_conf()` to write the register.
+    pub fn set_tbl(&mut self, tbl: u8) -> Result<(), Error<E>> {
+        if tbl > 3 || (tbl < 2 && self.chop_conf.toff() == 1) {
+            return Err(Error::InvalidValue);
+        }
+        self.chop_conf.set_tbl(tbl);
+        Ok(())
+    }
+
+    /// set the spreadCycle hysteresis in the CHOP_CONF shadow register (chm = 0)
+    ///
+    /// `hstrt` is the hysteresis start value added to HEND (1 to 8), `hend` the hysteresis end
+    /// value (-3 to 12). HSTRT + HEND must not exceed 16.
+    /// Call `update_chop_conf()` to write the register.
+    pub fn set_hysteresis(&mut self, hstrt: u8, hend: i8) -> Result<(), Error<E>> {
+        if !(1..=8).contains(&hstrt) || !(-3..=12).contains(&hend) || hstrt as i8 + hend > 16 {
+            return Err(Error::InvalidValue);
+        }
+        self.chop_conf.set_chm(false);
+        self.chop_conf.set_hstr(hstrt - 1);
+        self.chop_conf.set_hend((hend + 3) as u8);
+        Ok(())
+    }
+
+    /// set the fast decay time and sine wave offset in the CHOP_CONF shadow register (chm = 1)
+    ///
+    /// `tfd` is the fast decay time setting (0 to 15, in multiples of 32 clocks), `offset` the sine
+    /// wave offset (-3 to 12) and `comparator` terminates the fast decay with the current comparator
+    /// (disfdcc = 0). Call `update_chop_conf()` to write the register.
+    pub fn set_fast_decay(
+        &mut self,
+        tfd: u8,
+        offset: i8,
+        comparator: bool,
+    ) -> Result<(), Error<E>> {
+        if tfd > 15 || !(-3..=12).contains(&offset) {
+            return Err(Error::InvalidValue);
+        }
+        self.chop_conf.set_chm(true);
+        self.chop_conf.set_hstr(tfd & 0b111);
+        self.chop_conf.set_fd3(tfd & 0b1000 != 0);
+        self.chop_conf.set_hend((offset + 3) as u8);
+        self.chop_conf.set_disfdcc(!comparator);
+        Ok(())
+    }
+
+    /// set the microstep resolution (256, 128, 64, 32, 16, 8, 4, 2 or 1) in the CHOP_CONF shadow register
+    ///
+    /// Call `update_chop_conf()` to write the register.
+    pub fn set_microsteps(&mut self, microsteps: u16) -> Result<(), Error<E>> {
+        if !microsteps.is_power_of_two() || microsteps > 256 {
+            return Err(Error::InvalidValue);
+        }
+        self.chop_conf
+            .set_mres(8 - microsteps.trailing_zeros() as u8);
+        Ok(())
+    }
+
+    /// slow decay time in µs of the given TOFF setting at the configured clock
+    pub fn toff_to_us(&self, toff: u8) -> f32 {
+        toff_clocks(toff) as f32 / self._clock * 1_000_000.0
+    }
+
+    /// TOFF setting (1 to 15) closest to the given slow decay time in µs at the configured clock
+    pub fn toff_from_us(&self, time_us: f32) -> u8 {
+        let clocks = time_us * self._clock / 1_000_000.0;
+        ((clocks - 24.0) / 32.0 + 0.5).clamp(1.0, 15.0) as u8
+    }
+
+    /// blank time in ns of the given TBL setting at the configured clock
+    pub fn tbl_to_ns(&self, tbl: u8) -> f32 {
+        tbl_clocks(tbl) as f32 / self._clock * 1_000_000_000.0
+    }
+
+    /// smallest TBL setting (0 to 3) with a blank time of at least the given time in ns at the configured clock
+    pub fn tbl_from_ns(&self, time_ns: f32) -> u8 {
+        (0..3)
+            .find(|&tbl| self.tbl_to_ns(tbl) >= time_ns)
+            .unwrap_or(3)
+    }
+
+    /// set TOFF from a slow decay time in µs (see `set_toff()`)
+    pub fn set_toff_us(&mut self, time_us: f32) -> Result<(), Error<E>> {
+        let toff = self.toff_from_us(time_us);
+        self.set_toff(toff)
+    }
+
+    /// set TBL from a blank time in ns (see `set_tbl()`)
+    pub fn set_tbl_ns(&mut self, time_ns: f32) -> Result<(), Error<E>> {
+        let tbl = self.tbl_from_ns(time_ns);
+        self.set_tbl(tbl)
+    }
+
+    /// estimated chopper frequency in Hz resulting from TOFF and TBL of the CHOP_CONF shadow register
+    ///
+    /// One chopper period consists of at least two slow decay phases (TOFF) and two blank times (TBL).
+    /// The on and fast decay phases are load dependent and not included, so the real frequency is lower.
+    /// Returns 0 if the driver is disabled (TOFF = 0).
+    pub fn chopper_frequency(&self) -> f32 {
+        let toff = self.chop_conf.toff();
+        if toff == 0 {
+            return 0.0;
+        }
+        let period = 2 * (toff_clocks(toff) + tbl_clocks(self.chop_conf.tbl()));
+        self._clock / period as f32
+    }
+
+    /// write value to CHOP_CONF register
+    pub fn update_chop_conf(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.chop_conf.into_bytes());
+        self.write_register(Registers::CHOPCONF, &mut value)
+    }
+
+    /// write value to COOL_CONF register
+    pub fn update_cool_conf(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.cool_conf.into_bytes());
+        self.write_register(Registers::COOLCONF, &mut value)
+    }
+
+    /// write value to IHOLD_IRUN register (scaled by the torque limit, see `set_torque_limit_percent()`)
+    pub fn update_ihold_irun(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.effective_ihold_irun().into_bytes());
+        self.write_register(Registers::IHOLD_IRUN, &mut value)
+    }
+
+    fn effective_ihold_irun(&self) -> IHoldIRun {
+        let mut ihold_irun = self.ihold_irun;
+        let limit = self._torque_limit.min(self._thermal_limit);
+        if limit < 100 {
+            let i_run = (ihold_irun.i_run() as u16 * limit as u16 / 100) as u8;
+            ihold_irun.set_i_run(i_run);
+            ihold_irun.set_i_hold(ihold_irun.i_hold().min(i_run));
+        }
+        if self.pwm_conf.free_wheel() != FreeWheel::Normal as u8 {
+            ihold_irun.set_i_hold(0);
+        }
+        ihold_irun
+    }
+
+    /// limit the motor torque by scaling the run current of the IHOLD_IRUN shadow register to
+    /// `percent` (0 to 100) and write IHOLD_IRUN
+    ///
+    /// The shadow register keeps the nominal currents, 100 % removes the limit.
+    pub fn set_torque_limit_percent(&mut self, percent: u8) -> Result<DataPacket, Error<E>> {
+        if percent > 100 {
+            return Err(Error::InvalidValue);
+        }
+        self._torque_limit = percent;
+        self.update_ihold_irun()
+    }
+
+    /// get the active torque limit in percent of the nominal run current
+    pub fn get_torque_limit_percent(&self) -> u8 {
+        self._torque_limit
+    }
+
+    /// stealthChop PWM frequency in Hz of the PWM_CONF shadow register at the configured clock
+    pub fn pwm_frequency(&self) -> f32 {
+        PwmFreq::from_bits(self.pwm_conf.pwm_freq()).frequency(self._clock)
+    }
+
+    /// select the PWM frequency closest to `frequency` (Hz) in the PWM_CONF shadow register
+    ///
+    /// Returns the selected setting. Call `update_pwm_conf()` to write the register.
+    pub fn set_pwm_frequency(&mut self, frequency: f32) -> PwmFreq {
+        let freq = PwmFreq::closest(self._clock, frequency);
+        self.pwm_conf.set_pwm_freq(freq as u8);
+        freq
+    }
+
+    /// configure the standstill behaviour (freewheeling or passive braking)
+    ///
+    /// The freewheel options are only active with IHOLD = 0 and stealthChop enabled, so IHOLD is
+    /// written as 0 for all modes except `FreeWheel::Normal`. The IHOLD_IRUN shadow register keeps
+    /// the configured hold current, which is written again when returning to `FreeWheel::Normal`.
+    /// Writes IHOLD_IRUN and PWM_CONF.
+    pub fn set_standstill_mode(&mut self, mode: FreeWheel) -> Result<DataPacket, Error<E>> {
+        self.pwm_conf.set_free_wheel(mode as u8);
+        self.update_ihold_irun()?;
+        self.update_pwm_conf()
+    }
+
+    /// check if the standstill mode of the PWM_CONF shadow register is effective (IHOLD = 0)
+    pub fn standstill_mode_is_active(&self) -> bool {
+        self.pwm_conf.free_wheel() != FreeWheel::Normal as u8
+            && self.effective_ihold_irun().i_hold() == 0
+    }
+
+    /// set run current, hold current (0 to 31) and hold delay (0 to 15) and write IHOLD_IRUN
+    ///
+    /// The hold current must not exceed the run current. Run currents below 16 reduce the microstep
+    /// resolution, this can be checked with `run_current_is_low()`.
+    pub fn set_currents(&mut self, run: u8, hold: u8, delay: u8) -> Result<DataPacket, Error<E>> {
+        if run > 31 || hold > run || delay > 15 {
+            return Err(Error::InvalidValue);
+        }
+        self.ihold_irun.set_i_run(run);
+        self.ihold_irun.set_i_hold(hold);
+        self.ihold_irun.set_i_hold_delay(delay);
+        self.update_ihold_irun()
+    }
+
+    /// set the RMS run current and the hold current and write IHOLD_IRUN
+    ///
+    /// IRUN is the code closest to `run` within the current range of GLOBALSCALER and the sense
+    /// resistor (see `effective_current_range()`), IHOLD the code closest to `hold` percent of it.
+    /// The hold delay is kept. Returns `Error::NotConfigured` without a sense resistor and
+    /// `Error::InvalidValue` if `run` is outside of the range.
+    pub fn set_rms_current(
+        &mut self,
+        run: RmsCurrent,
+        hold: HoldPercent,
+    ) -> Result<DataPacket, Error<E>> {
+        let range = self.effective_current_range().ok_or(Error::NotConfigured)?;
+        let steps = run.amps() / range * 32.0;
+        if !(0.5..=32.5).contains(&steps) {
+            return Err(Error::InvalidValue);
+        }
+        let i_run = ((steps + 0.5) as u8).clamp(1, 32) - 1;
+        let i_hold =
+            (((i_run as f32 + 1.0) * hold.percent() as f32 / 100.0 + 0.5) as u8).max(1) - 1;
+        let delay = self.ihold_irun.i_hold_delay();
+        self.set_currents(i_run, i_hold, delay)
+    }
+
+    /// RMS run current of the IHOLD_IRUN shadow register, `None` without a sense resistor
+    pub fn get_rms_current(&self) -> Option<RmsCurrent> {
+        let i_run = self.ihold_irun.i_run() as f32;
+        self.effective_current_range().and_then(|range| {
+            RmsCurrent::from_ma((range * (i_run + 1.0) / 32.0 * 1000.0 + 0.5) as u16)
+        })
+    }
+
+    /// check if the run current of the IHOLD_IRUN shadow register is below 16, which reduces the
+    /// microstep resolution (increase GLOBALSCALER or use a smaller sense resistor instead)
+    pub fn run_current_is_low(&self) -> bool {
+        self.ihold_irun.i_run() < 16
+    }
+
+    /// enable (`Some`) or disable (`None`) the automatic standstill current reduction
+    ///
+    /// Once enabled, `update_standstill_reduction()` has to be called periodically.
+    pub fn set_standstill_reduction(
+        &mut self,
+        reduction: Option<StandstillReduction>,
+    ) -> Result<(), Error<E>> {
+        if let Some(reduction) = reduction {
+            if reduction.deep_hold > 31 {
+                return Err(Error::InvalidValue);
+            }
+        }
+        self.restore_run_current()?;
+        self._standstill_reduction = reduction;
+        Ok(())
+    }
+
+    /// poll the standstill state and reduce the hold current to the deep hold level once the motor
+    /// has been at standstill for the configured time
+    ///
+    /// `now_ms` is a monotonic millisecond timestamp of the application. The IHOLD_IRUN shadow
+    /// register is left untouched and written again before the next motion command.
+    /// Returns true while the deep hold current is active.
+    pub fn update_standstill_reduction(&mut self, now_ms: u32) -> Result<bool, Error<E>> {
+        let reduction = match self._standstill_reduction {
+            Some(reduction) => reduction,
+            None => return Ok(false),
+        };
+        if self._deep_hold_active {
+            return Ok(true);
+        }
+        if !self.read_drv_status()?.standstill() {
+            self._standstill_since = None;
+            return Ok(false);
+        }
+        let since = *self._standstill_since.get_or_insert(now_ms);
+        if now_ms.wrapping_sub(since) >= reduction.delay_ms {
+            let mut ihold_irun = self.effective_ihold_irun();
+            ihold_irun.set_i_hold(reduction.deep_hold.min(ihold_irun.i_hold()));
+            let mut value = swap_bytes(ihold_irun.into_bytes());
+            self.write_register(Registers::IHOLD_IRUN, &mut value)?;
+            self._deep_hold_active = true;
+        }
+        Ok(self._deep_hold_active)
+    }
+
+    fn restore_run_current(&mut self) -> Result<(), Error<E>> {
+        self._standstill_since = None;
+        if self._park_free_wheel {
+            self.update_pwm_conf()?;
+            self._park_free_wheel = false;
+        }
+        if self._deep_hold_active {
+            self.update_ihold_irun()?;
+            self._deep_hold_active = false;
+        }
+        Ok(())
+    }
+
+    /// move to the park position, wait for standstill and reduce the current to `hold`
+    ///
+    /// The shadow registers are left untouched, the currents and PWMCONF are restored with
+    /// `unpark()` or before the next motion command. Returns `Error::Timeout` if the motor does
+    /// not reach the park position before the deadline.
+    pub fn park(
+        &mut self,
+        position: f32,
+        hold: ParkHold,
+        deadline: &mut impl Deadline,
+    ) -> Result<(), Error<E>> {
+        if let ParkHold::Current(current) = hold {
+            if current > 31 {
+                return Err(Error::InvalidValue);
+            }
+        }
+        self.move_to(position)?;
+        self.wait_until(deadline, Self::is_parked)?;
+        self.park_hold(hold)
+    }
+
+    /// async variant of `park()`, awaits the embassy-time timer between the polls
+    #[cfg(feature = "embassy")]
+    pub async fn park_async(
+        &mut self,
+        position: f32,
+        hold: ParkHold,
+        deadline: &TimerDeadline,
+    ) -> Result<(), Error<E>> {
+        if let ParkHold::Current(current) = hold {
+            if current > 31 {
+                return Err(Error::InvalidValue);
+            }
+        }
+        self.move_to(position)?;
+        self.wait_until_async(deadline, Self::is_parked).await?;
+        self.park_hold(hold)
+    }
+
+    fn is_parked(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.position_is_reached()? && self.is_at_standstill()?)
+    }
+
+    /// reduce the current after the park position is reached
+    fn park_hold(&mut self, hold: ParkHold) -> Result<(), Error<E>> {
+        let mut ihold_irun = self.effective_ihold_irun();
+        match hold {
+            ParkHold::Current(current) => ihold_irun.set_i_hold(current.min(ihold_irun.i_hold())),
+            ParkHold::FreeWheel(mode) => {
+                let mut pwm_conf = self.pwm_conf;
+                pwm_conf.set_free_wheel(mode as u8);
+                let mut value = swap_bytes(pwm_conf.into_bytes());
+                self.write_register(Registers::PWMCONF, &mut value)?;
+                self._park_free_wheel = true;
+                ihold_irun.set_i_hold(0);
+            }
+        }
+        let mut value = swap_bytes(ihold_irun.into_bytes());
+        self.write_register(Registers::IHOLD_IRUN, &mut value)?;
+        self._deep_hold_active = true;
+        Ok(())
+    }
+
+    /// restore the currents of the shadow registers after `park()`
+    pub fn unpark(&mut self) -> Result<(), Error<E>> {
+        self.restore_run_current()
+    }
+
+    /// write value to SHORT_CONF register
+    pub fn update_short_conf(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.short_conf.into_bytes());
+        self.write_register(Registers::SHORT_CONF, &mut value)
+    }
+
+    /// write value to DRV_CONF register
+    pub fn update_drv_conf(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.drv_conf.into_bytes());
+        self.write_register(Registers::DRV_CONF, &mut value)
+    }
+
+    /// write value to MSLUTSEL register
+    pub fn update_ms_lut_sel(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.ms_lut_sel.into_bytes());
+        self.write_register(Registers::MSLUTSEL, &mut value)
+    }
+
+    /// write value to MSLUTSTART register
+    pub fn update_ms_lut_start(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.ms_lut_start.into_bytes());
+        self.write_register(Registers::MSLUTSTART, &mut value)
+    }
+
+    /// write value to DCCTRL register
+    pub fn update_dc_ctrl(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.dc_ctrl.into_bytes());
+        self.write_register(Registers::DCCTRL, &mut value)
+    }
+
+    /// write value to SLAVECONF register (UART node address and send delay)
+    pub fn update_node_conf(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.node_conf.into_bytes());
+        self.write_register(Registers::SLAVECONF, &mut value)
+    }
+
+    /// set the UART node address (0 to 253) and write SLAVECONF
+    ///
+    /// With the NEXTADDR pin high, the chip uses the address + 1.
+    pub fn set_node_address(&mut self, address: u8) -> Result<DataPacket, Error<E>> {
+        if address > 253 {
+            return Err(Error::InvalidValue);
+        }
+        self.node_conf.set_nodeaddr(address);
+        self.update_node_conf()
+    }
+
+    /// set the UART send delay and write SLAVECONF
+    ///
+    /// On a bus with several nodes (`multi_drop`), the delay has to be at least 24 bit times, so
+    /// the master can release the bus before the reply.
+    pub fn set_send_delay(
+        &mut self,
+        delay: SendDelay,
+        multi_drop: bool,
+    ) -> Result<DataPacket, Error<E>> {
+        if multi_drop && !delay.is_multi_drop_safe() {
+            return Err(Error::InvalidValue);
+        }
+        self.node_conf.set_senddelay(delay as u8);
+        self.update_node_conf()
+    }
+
+    /// get the UART send delay of the SLAVECONF shadow register
+    pub fn get_send_delay(&self) -> SendDelay {
+        SendDelay::from_bits(self.node_conf.senddelay())
+    }
+
+    /// write value to PWM_CONF register
+    pub fn update_pwm_conf(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.pwm_conf.into_bytes());
+        self.write_register(Registers::PWMCONF, &mut value)
+    }
+
+    /// write value to ENC_MODE register
+    pub fn update_enc_mode(&mut self) -> Result<DataPacket, Error<E>> {
+        let mut value = swap_bytes(self.enc_mode.into_bytes());
+        self.write_register(Registers::ENCMODE, &mut value)
+    }
+
+    /// write all shadow registers to the chip
+    ///
+    /// Writes GCONF, GLOBALSCALER (if available), IHOLD_IRUN, CHOPCONF, COOLCONF, PWMCONF, SW_MODE and ENCMODE.
+    pub fn update_config(&mut self) -> Result<DataPacket, Error<E>> {
+        self.update_g_conf()?;
+        if CHIP.has_register(Registers::GLOBALSCALER.addr()) {
+            self.set_global_scaler(self._global_scaler as u32)?;
+        }
+        self.update_ihold_irun()?;
+        self.update_chop_conf()?;
+        self.update_cool_conf()?;
+        self.update_pwm_conf()?;
+        self.update_sw_mode()?;
+        self.update_enc_mode()
+    }
+
+    /// recover from a major fault by restarting the driver with the shadowed configuration
+    ///
+    /// Disables the power stage (EN pin and TOFF = 0), clears GSTAT, the RAMP_STAT events and
+    /// ENC_STATUS, writes GCONF, SLAVECONF, SHORT_CONF, DRV_CONF, GLOBALSCALER, IHOLD_IRUN, COOLCONF,
+    /// PWMCONF, DCCTRL, SW_MODE and ENCMODE and finally CHOPCONF, which starts the power stage
+    /// again, then re-enables the EN pin. Fails with `Error::EmergencyStop` while an emergency stop
+    /// is latched.
+    pub fn reboot(&mut self) -> Result<(), Error<E>> {
+        self.check_emergency_stop()?;
+        self.disable()?;
+        let mut value = swap_bytes(self.chop_conf.with_toff(0).into_bytes());
+        self.write_register(Registers::CHOPCONF, &mut value)?;
+        self.clear_g_stat()?;
+        self.clear_all_ramp_events()?;
+        self.clear_enc_status()?;
+        self.update_g_conf()?;
+        self.update_node_conf()?;
+        if CHIP.has_register(Registers::DRV_CONF.addr()) {
+            self.update_short_conf()?;
+            self.update_drv_conf()?;
+            self.set_global_scaler(self._global_scaler as u32)?;
+        }
+        self.update_ihold_irun()?;
+        self.update_cool_conf()?;
+        self.update_pwm_conf()?;
+        self.update_dc_ctrl()?;
+        self.update_sw_mode()?;
+        self.update_enc_mode()?;
+        self.update_chop_conf()?;
+        self._deep_hold_active = false;
+        self._standstill_since = None;
+        self._park_free_wheel = false;
+        self.enable()
+    }
+
+    /// read back all readable shadowed registers (GCONF, CHOPCONF, SW_MODE, ENCMODE) and compare
+    /// them with the shadow registers
+    pub fn verify_config(&mut self) -> Result<Mismatches, Error<E>> {
+        let expected = [
+            (Registers::GCONF, self.g_conf.into_bytes()),
+            (Registers::CHOPCONF, self.chop_conf.into_bytes()),
+            (Registers::SW_MODE, self.sw_mode.into_bytes()),
+            (Registers::ENCMODE, self.enc_mode.into_bytes()),
+        ];
+        let mut mismatches = Mismatches::default();
+        for (register, bytes) in expected {
+            let packet = self.read_register(register)?;
+            self.status = packet.status;
+            let expected = u32::from_le_bytes(bytes);
+            if packet.data != expected {
+                mismatches.push(Mismatch {
+                    register,
+                    expected,
+                    actual: packet.data,
+                });
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// populate the shadow registers from the chip, e.g. when attaching to a chip that has been
+    /// configured before a reset of the microcontroller
+    ///
+    /// Reads GCONF, CHOPCONF, SW_MODE and ENCMODE. The other configuration registers (IHOLD_IRUN,
+    /// COOLCONF, PWMCONF, DRV_CONF, ...) are write-only and keep their shadow values, so they have
+    /// to be set again before writing them, e.g. with `update_config()`.
+    pub fn sync_from_chip(&mut self) -> Result<(), Error<E>> {
+        self.g_conf = self.read_gconf()?;
+        let packet = self.read_register(Registers::CHOPCONF)?;
+        self.chop_conf = ChopConf::from_bytes(packet.data.to_le_bytes());
+        let packet = self.read_register(Registers::SW_MODE)?;
+        self.sw_mode = SwMode::from_bytes(packet.data.to_le_bytes());
+        let packet = self.read_register(Registers::ENCMODE)?;
+        self.enc_mode = EncMode::from_bytes(packet.data.to_le_bytes());
+        self.status = packet.status;
+        Ok(())
+    }
+
+    /// write the power-on defaults of the datasheet to all writable registers and reset the shadow
+    /// registers to them
+    ///
+    /// The defaults taken from OTP (TBL, BBMCLKS and the short detection levels) are read from
+    /// OTP_READ, OTP_PROG and FACTORY_CONF are not touched. GSTAT is cleared. The motor has to be
+    /// at standstill, as VMAX and AMAX are set to 0.
+    pub fn reset_to_defaults(&mut self) -> Result<(), Error<E>> {
+        if !self.read_drv_status()?.standstill() {
+            return Err(Error::NotAtStandstill);
+        }
+        let has_otp = CHIP.has_register(Registers::OTP_READ.addr());
+        let otp = if has_otp {
+            self.read_otp()?
+        } else {
+            OtpRead::new()
+        };
+        let (g_conf, pwm_conf) = match CHIP {
+            Chip::Tmc5130 => (0, 0x0005_0480),
+            _ => (0x0000_0008, 0xC40C_001E),
+        };
+        let chop_conf = ChopConf::default().with_tbl(otp.default_tbl());
+        let short_conf = ShortConf::new()
+            .with_s2vs_level(otp.default_s2_level())
+            .with_s2g_level(otp.default_s2_level())
+            .with_shortfilter(1);
+        let drv_conf = DrvConf::new()
+            .with_bbm_clks(otp.default_bbm_clks())
+            .with_drv_strength(2);
+        let table = MicrostepTable::default();
+        let defaults = [
+            // stop the ramp generator first
+            (Registers::VMAX, 0),
+            (Registers::AMAX, 0),
+            (Registers::RAMPMODE, 0),
+            (Registers::GCONF, g_conf),
+            (Registers::SLAVECONF, 0),
+            (Registers::X_COMPARE, 0),
+            (
+                Registers::SHORT_CONF,
+                u32::from_le_bytes(short_conf.into_bytes()),
+            ),
+            (
+                Registers::DRV_CONF,
+                u32::from_le_bytes(drv_conf.into_bytes()),
+            ),
+            (Registers::GLOBALSCALER, 0),
+            (Registers::IHOLD_IRUN, 0),
+            (Registers::TPOWERDOWN, 10),
+            (Registers::TPWMTHRS, 0),
+            (Registers::TCOOLTHRS, 0),
+            (Registers::THIGH, 0),
+            (Registers::XACTUAL, 0),
+            (Registers::VSTART, 0),
+            (Registers::A1, 0),
+            (Registers::V1, 0),
+            (Registers::DMAX, 0),
+            (Registers::D1, 0),
+            (Registers::VSTOP, 0),
+            (Registers::TZEROWAIT, 0),
+            (Registers::XTARGET, 0),
+            (Registers::VDCMIN, 0),
+            (Registers::SW_MODE, 0),
+            (Registers::ENCMODE, 0),
+            (Registers::X_ENC, 0),
+            (Registers::ENC_CONST, 0x0001_0000),
+            (Registers::ENC_DEVIATION, 0),
+            (Registers::MSLUT_0_7, table.mslut[0]),
+            (Registers::MSLUT_1, table.mslut[1]),
+            (Registers::MSLUT_2, table.mslut[2]),
+            (Registers::MSLUT_3, table.mslut[3]),
+            (Registers::MSLUT_4, table.mslut[4]),
+            (Registers::MSLUT_5, table.mslut[5]),
+            (Registers::MSLUT_6, table.mslut[6]),
+            (Registers::MSLUT_7, table.mslut[7]),
+            (
+                Registers::MSLUTSEL,
+                u32::from_le_bytes(table.sel.into_bytes()),
+            ),
+            (
+                Registers::MSLUTSTART,
+                u32::from_le_bytes(table.start.into_bytes()),
+            ),
+            (
+                Registers::CHOPCONF,
+                u32::from_le_bytes(chop_conf.into_bytes()),
+            ),
+            (Registers::COOLCONF, 0),
+            (Registers::DCCTRL, 0),
+            (Registers::PWMCONF, pwm_conf),
+        ];
+        for (register, value) in defaults {
+            if CHIP.has_register(register.addr()) {
+                self.write_register(register, &mut value.to_be_bytes())?;
+            }
+        }
+        self.clear_g_stat()?;
+
+        self.g_conf = GConf::from_bytes(g_conf.to_le_bytes());
+        self.node_conf = NodeConf::new();
+        self.otp_prog = OtpProg::new();
+        self.short_conf = short_conf;
+        self.drv_conf = drv_conf;
+        self.ihold_irun = IHoldIRun::new();
+        self.sw_mode = SwMode::new();
+        self.enc_mode = EncMode::new();
+        self.ms_lut_sel = table.sel;
+        self.ms_lut_start = table.start;
+        self.dc_ctrl = DcCtrl::new();
+        self.chop_conf = chop_conf;
+        self.cool_conf = CoolConf::new();
+        self.pwm_conf = PwmConf::from_bytes(pwm_conf.to_le_bytes());
+        self._global_scaler = 0;
+        self.v_max = 0.0;
+        self._v_max_nominal = None;
+        self._a_max = None;
+        self._move_defaults = None;
+        self._deep_hold_active = false;
+        self._standstill_since = None;
+        self._park_free_wheel = false;
+        self.reset_backlash();
+        self.reset_encoder_velocity();
+        Ok(())
+    }
+
+    /// check GCONF and CHOPCONF against the shadow registers and repair them if they differ
+    ///
+    /// If the chip reports a reset, the whole shadow configuration is written again (see
+    /// `update_config()`) and GSTAT is cleared. Returns true if a repair occurred.
+    pub fn watchdog_tick(&mut self) -> Result<bool, Error<E>> {
+        let g_conf = self.read_gconf()?;
+        if self.status.reset_flag() {
+            self.update_config()?;
+            self.clear_g_stat()?;
+            return Ok(true);
+        }
+        let mut repaired = false;
+        if g_conf.into_bytes() != self.g_conf.into_bytes() {
+            self.update_g_conf()?;
+            repaired = true;
+        }
+        let packet = self.read_register(Registers::CHOPCONF)?;
+        self.status = packet.status;
+        if packet.data.to_le_bytes() != self.chop_conf.into_bytes() {
+            self.update_chop_conf()?;
+            repaired = true;
+        }
+        Ok(repaired)
+    }
+
+    /// select the background duties of `tick()`, none are enabled by default
+    pub fn tick_duties(mut self, duties: TickDuties) -> Self {
+        self._tick_duties = duties;
+        self
+    }
+
+    /// perform the next enabled background duty, to be called at a fixed rate
+    ///
+    /// The duties are run one per call in turn, so a call takes at most two register reads and two
+    /// writes, unless the watchdog rewrites the whole configuration after a reset. With `n` enabled
+    /// duties, each one runs every `n`th call. `now_ms` is a monotonic millisecond timestamp of
+    /// the application, it is also used by the read cache (see `set_time_ms()`). Returns `None` if no duty is enabled.
+    pub fn tick(&mut self, now_ms: u32) -> Result<Option<TickDuty>, Error<E>> {
+        self._now_ms = now_ms;
+        let duties = self._tick_duties;
+        let enabled = [
+            duties.status,
+            duties.standstill,
+            duties.watchdog,
+            duties.thermal.is_some(),
+            duties.counters,
+            duties.cache,
+        ];
+        for _ in 0..enabled.len() {
+            let slot = self._tick_slot;
+            self._tick_slot = (slot + 1) % enabled.len() as u8;
+            if !enabled[slot as usize] {
+                continue;
+            }
+            let duty = match slot {
+                0 => TickDuty::Status(self.read_drv_status()?),
+                1 => TickDuty::Standstill(self.update_standstill_reduction(now_ms)?),
+                2 => TickDuty::Watchdog(self.watchdog_tick()?),
+                3 => TickDuty::Thermal(self.thermal_tick(duties.thermal.unwrap_or(100))?),
+                4 => {
+                    self.poll_stall()?;
+                    TickDuty::Counters(self._counters)
+                }
+                _ => {
+                    // drop the cached values, so the reads go to the chip
+                    self._cached[1] = None;
+                    self._cached[2] = None;
+                    self.read_ramp_status()?;
+                    self.read_register(Registers::XACTUAL)?;
+                    TickDuty::Cache
+                }
+            };
+            return Ok(Some(duty));
+        }
+        Ok(None)
+    }
+
+    /// limit the run current to `percent` while an overtemperature (pre-)warning is reported
+    fn thermal_tick(&mut self, percent: u8) -> Result<bool, Error<E>> {
+        let drv_status = self.read_drv_status()?;
+        let limit = if drv_status.otpw() || drv_status.ot() {
+            percent.min(100)
+        } else {
+            100
+        };
+        if limit != self._thermal_limit {
+            self._thermal_limit = limit;
+            self.update_ihold_irun()?;
+        }
+        Ok(limit < 100)
+    }
+
+    /// program one bit of OTP byte 0 (bit 0 to 7) and check the result in OTP_READ
+    ///
+    /// **Programming the OTP memory is irreversible.** The programming time of 10 ms is waited with
+    /// `delay`, afterwards OTP_PROG is cleared again. Returns true if the bit reads back as set.
+    #[cfg(feature = "unsafe_otp")]
+    pub fn program_otp_bit<D: DelayMs<u32>>(
+        &mut self,
+        bit: u8,
+        delay: &mut D,
+    ) -> Result<bool, Error<E>> {
+        if bit > 7 {
+            return Err(Error::InvalidValue);
+        }
+        self.otp_prog.set_otpbit(bit);
+        self.otp_prog.set_otpbyte(0);
+        self.otp_prog.set_otpmagic(0xBD);
+        let mut value = swap_bytes(self.otp_prog.into_bytes());
+        self.write_register(Registers::OTP_PROG, &mut value)?;
+        delay.delay_ms(10);
+        self.otp_prog = OtpProg::new();
+        let mut value = 0_u32.to_be_bytes();
+        self.write_register(Registers::OTP_PROG, &mut value)?;
+        let otp = self.read_otp()?;
+        Ok(otp.into_bytes()[0] & (1 << bit) != 0)
+    }
+
+    /// write value to GLOBALSCALER register (0: full scale, 32 to 255: scaling in multiples of 1/256)
+    pub fn set_global_scaler(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        if (1..32).contains(&val) || val > 255 {
+            return Err(Error::InvalidValue);
+        }
         let mut value = val.to_be_bytes();
+        let packet = self.write_register(Registers::GLOBALSCALER, &mut value)?;
+        self._global_scaler = val as u8;
+        Ok(packet)
+    }
+
+    /// get the last written GLOBALSCALER value (the register is write only)
+    pub fn get_global_scaler(&self) -> u8 {
+        self._global_scaler
+    }
+
+    /// full scale RMS current in A implied by GLOBALSCALER and the sense resistor (at IRUN = 31)
+    ///
+    /// Returns `None` if no sense resistor was specified.
+    pub fn effective_current_range(&self) -> Option<f32> {
+        let scaler = match self._global_scaler {
+            0 => 256.0,
+            scaler => scaler as f32,
+        };
+        let v_fs = CHIP.full_scale_voltage(self.chop_conf.vsense());
+        self._r_sense
+            .map(|r_sense| scaler / 256.0 * v_fs / r_sense / core::f32::consts::SQRT_2)
+    }
+
+    /// RMS run current in A implied by GLOBALSCALER, the sense resistor and the active IRUN
+    ///
+    /// Returns `None` if no sense resistor was specified.
+    pub fn effective_run_current(&self) -> Option<f32> {
+        let i_run = self.effective_ihold_irun().i_run() as f32;
+        self.effective_current_range()
+            .map(|range| range * (i_run + 1.0) / 32.0)
+    }
+
+    /// write value to TPOWERDOWN register (0 to 255)
+    pub fn set_tpowerdown(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        let mut value = checked_range(val, 0, 0xFF)?.to_be_bytes();
+        self.write_register(Registers::TPOWERDOWN, &mut value)
+    }
+
+    /// set the power down delay after standstill in ms (TPOWERDOWN, 0 to 255 * 2^18 clocks)
+    pub fn set_tpowerdown_ms(&mut self, delay_ms: f32) -> Result<DataPacket, Error<E>> {
+        let tpowerdown = (delay_ms / 1000.0 * self._clock / 262_144.0 + 0.5) as u32;
+        if delay_ms < 0.0 || tpowerdown > 255 {
+            return Err(Error::InvalidValue);
+        }
+        self.set_tpowerdown(tpowerdown)
+    }
+
+    /// set the delay per current reduction step from run to hold current in ms (IHOLDDELAY, 0 to 15 * 2^18 clocks)
+    pub fn set_ihold_delay_ms(&mut self, delay_ms: f32) -> Result<DataPacket, Error<E>> {
+        let ihold_delay = (delay_ms / 1000.0 * self._clock / 262_144.0 + 0.5) as u32;
+        if delay_ms < 0.0 || ihold_delay > 15 {
+            return Err(Error::InvalidValue);
+        }
+        self.ihold_irun.set_i_hold_delay(ihold_delay as u8);
+        self.update_ihold_irun()
+    }
+
+    /// write value to TPWMTHRS register (0 to 2^20 - 1)
+    pub fn set_tpwmthrs(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        let mut value = checked_range(val, 0, 0xF_FFFF)?.to_be_bytes();
+        self.write_register(Registers::TPWMTHRS, &mut value)
+    }
+
+    /// write value to TCOOLTHRS register (0 to 2^20 - 1)
+    pub fn set_tcoolthrs(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        let mut value = checked_range(val, 0, 0xF_FFFF)?.to_be_bytes();
+        self.write_register(Registers::TCOOLTHRS, &mut value)
+    }
+
+    /// write value to THIGH register (0 to 2^20 - 1)
+    pub fn set_thigh(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        let mut value = checked_range(val, 0, 0xF_FFFF)?.to_be_bytes();
+        self.write_register(Registers::THIGH, &mut value)
+    }
+
+    /// write value to A1 register (0 to 2^16 - 1)
+    pub fn set_a1(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        let mut value = checked_range(val, 0, 0xFFFF)?.to_be_bytes();
         self.write_register(Registers::A1, &mut value)
     }
 
-    /// write value to V1 register
+    /// write value to V1 register (0 to 2^20 - 1)
     pub fn set_v1(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
+        let mut value = checked_range(val, 0, 0xF_FFFF)?.to_be_bytes();
         self.write_register(Registers::V1, &mut value)
     }
 
-    /// write value to AMAX register
+    /// write value to AMAX register (0 to 2^16 - 1)
     pub fn set_amax(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
-        self.write_register(Registers::AMAX, &mut value)
+        let mut value = checked_range(val, 0, 0xFFFF)?.to_be_bytes();
+        let packet = self.write_register(Registers::AMAX, &mut value)?;
+        self._a_max = Some(val);
+        Ok(packet)
     }
 
-    /// write value to VMAX register
+    /// write value to VMAX register (0 to 2^23 - 512)
     pub fn set_vmax(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
+        self.check_emergency_stop()?;
+        let mut value = checked_range(val, 0, VMAX_LIMIT)?.to_be_bytes();
         self.write_register(Registers::VMAX, &mut value)
     }
 
-    /// write value to DMAX register
+    /// write value to DMAX register (0 to 2^16 - 1)
     pub fn set_dmax(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
+        let mut value = checked_range(val, 0, 0xFFFF)?.to_be_bytes();
         self.write_register(Registers::DMAX, &mut value)
     }
 
-    /// write value to D1 register
+    /// write value to D1 register (1 to 2^16 - 1, do not set 0 in positioning mode)
     pub fn set_d1(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
+        let mut value = checked_range(val, 1, 0xFFFF)?.to_be_bytes();
         self.write_register(Registers::D1, &mut value)
     }
 
-    /// write value to VSTART register
+    /// write value to VSTART register (0 to 2^18 - 1)
     pub fn set_vstart(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
+        let mut value = checked_range(val, 0, 0x3_FFFF)?.to_be_bytes();
         self.write_register(Registers::VSTART, &mut value)
     }
 
-    /// write value to VSTOP register
+    /// write value to VSTOP register (10 to 2^18 - 1, keep VSTOP >= VSTART)
     pub fn set_vstop(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
-        let mut value = val.to_be_bytes();
+        let mut value = checked_range(val, 10, 0x3_FFFF)?.to_be_bytes();
         self.write_register(Registers::VSTOP, &mut value)
     }
 
+    /// write value to TZEROWAIT register (0 to 2^16 - 1)
+    pub fn set_tzerowait(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        let mut value = checked_range(val, 0, 0xFFFF)?.to_be_bytes();
+        self.write_register(Registers::TZEROWAIT, &mut value)
+    }
+
+    /// write value to X_COMPARE register
+    pub fn set_x_compare(&mut self, val: i32) -> Result<DataPacket, Error<E>> {
+        let mut value = val.to_be_bytes();
+        self.write_register(Registers::X_COMPARE, &mut value)
+    }
+
+    /// write value to VDCMIN register (0 to 2^23 - 1)
+    pub fn set_vdcmin(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        let mut value = checked_range(val, 0, 0x7F_FFFF)?.to_be_bytes();
+        self.write_register(Registers::VDCMIN, &mut value)
+    }
+
+    /// write value to MSLUT[index] register (index 0 to 7)
+    pub fn set_mslut(&mut self, index: usize, val: u32) -> Result<DataPacket, Error<E>> {
+        let register = *Registers::MSLUT.get(index).ok_or(Error::InvalidValue)?;
+        let mut value = val.to_be_bytes();
+        self.write_register(register, &mut value)
+    }
+
+    /// set the ENCMODE shadow register to `mode` and write it
+    ///
+    /// Returns `Error::InvalidValue` without writing if `mode` combines mutually exclusive options,
+    /// see `EncMode::is_valid()`.
+    pub fn set_enc_mode(&mut self, mode: EncMode) -> Result<DataPacket, Error<E>> {
+        if !mode.is_valid() {
+            return Err(Error::InvalidValue);
+        }
+        self.enc_mode = mode;
+        self.update_enc_mode()
+    }
+
+    /// write value to ENC_CONST register
+    pub fn set_enc_const(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        let mut value = val.to_be_bytes();
+        self.write_register(Registers::ENC_CONST, &mut value)
+    }
+
+    /// write value to ENC_DEVIATION register
+    pub fn set_enc_deviation(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
+        let mut value = val.to_be_bytes();
+        self.write_register(Registers::ENC_DEVIATION, &mut value)
+    }
+
+    /// write value to X_ENC register
+    pub fn set_x_enc(&mut self, val: i32) -> Result<DataPacket, Error<E>> {
+        self.reset_encoder_velocity();
+        let mut value = val.to_be_bytes();
+        self.write_register(Registers::X_ENC, &mut value)
+    }
+
+    /// read X_ENC register (actual encoder position)
+    pub fn read_x_enc(&mut self) -> Result<i32, Error<E>> {
+        let packet = self.read_register(Registers::X_ENC)?;
+        self.status = packet.status;
+        Ok(packet.data as i32)
+    }
+
+    /// estimate the mechanical velocity from the change of X_ENC since the previous call
+    ///
+    /// `dt_ms` is the time since the previous call. The result is in the unit of `get_velocity()`,
+    /// so a difference between both shows slip, e.g. of a belt. ENC_CONST has to be set up so
+    /// X_ENC counts in microsteps. Returns `None` on the first call.
+    pub fn estimate_encoder_velocity(&mut self, dt_ms: u32) -> Result<Option<f32>, Error<E>> {
+        if dt_ms == 0 {
+            return Err(Error::InvalidValue);
+        }
+        let x_enc = self.read_x_enc()?;
+        let previous = self._x_enc_sample.replace(x_enc);
+        Ok(previous.map(|previous| {
+            x_enc.wrapping_sub(previous) as f32 / self._step_count * 1000.0 / dt_ms as f32
+        }))
+    }
+
+    /// forget the X_ENC sample of `estimate_encoder_velocity()`, e.g. after a pause
+    pub fn reset_encoder_velocity(&mut self) {
+        self._x_enc_sample = None;
+    }
+
+    /// read ENC_LATCH register (encoder position latched on N event)
+    pub fn read_enc_latch(&mut self) -> Result<i32, Error<E>> {
+        let packet = self.read_register(Registers::ENC_LATCH)?;
+        self.status = packet.status;
+        Ok(packet.data as i32)
+    }
+
+    /// read LOST_STEPS register (steps skipped in dcStep, only with SD_MODE = 1)
+    pub fn read_lost_steps(&mut self) -> Result<u32, Error<E>> {
+        let packet = self.read_register(Registers::LOST_STEPS)?;
+        self.status = packet.status;
+        Ok(packet.data & 0xFFFFF)
+    }
+
     /// write value to PWM_AUTO register
     pub fn set_pwm_auto(&mut self, val: u32) -> Result<DataPacket, Error<E>> {
         let mut value = val.to_be_bytes();
@@ -472,15 +2672,107 @@ where
     }
 
     /// write value to RAMPMODE register
+    ///
+    /// With soft limits (see `set_soft_limits()`), the velocity modes are refused with
+    /// `Error::SoftLimit`, use `run_to_soft_limit()` for a velocity move bounded by the limits.
     pub fn set_rampmode(&mut self, val: RampMode) -> Result<DataPacket, Error<E>> {
+        self.check_emergency_stop()?;
+        self.check_velocity_mode(val)?;
         let mut value = (val as u32).to_be_bytes();
         self.write_register(Registers::RAMPMODE, &mut value)
     }
 
-    /// read offset register
-    pub fn read_offset(&mut self) -> Result<u32, Error<E>> {
-        self.read_register(Registers::OFFSET_READ)
-            .map(|packet| packet.data)
+    /// read RAMPMODE register (the mode the ramp generator is actually in)
+    pub fn read_rampmode(&mut self) -> Result<RampMode, Error<E>> {
+        let packet = self.read_register(Registers::RAMPMODE)?;
+        self.status = packet.status;
+        Ok(RampMode::from_bits(packet.data as u8))
+    }
+
+    /// switch from a velocity mode to positioning mode without moving the motor
+    ///
+    /// Requires standstill, otherwise `Error::NotAtStandstill` is returned. XTARGET is set to
+    /// XACTUAL before RAMPMODE is selected, so the ramp generator does not run to a stale target,
+    /// then VMAX is restored from the last `set_velocity()` (e.g. after a stop with VMAX = 0).
+    pub fn switch_to_positioning(&mut self) -> Result<DataPacket, Error<E>> {
+        self.check_emergency_stop()?;
+        if !self.read_drv_status()?.standstill() {
+            return Err(Error::NotAtStandstill);
+        }
+        let mut position = self.read_register(Registers::XACTUAL)?.data.to_be_bytes();
+        self.write_register(Registers::XTARGET, &mut position)?;
+        let packet = self.set_rampmode(RampMode::PositioningMode)?;
+        self.restore_v_max()?;
+        self.status = packet.status;
+        Ok(packet)
+    }
+
+    /// switch to the velocity mode `mode` (`VelocityModePos`, `VelocityModeNeg` or `HoldMode`)
+    ///
+    /// VMAX is restored from the last `set_velocity()` before RAMPMODE is selected, so the motor
+    /// accelerates with AMAX to the intended velocity instead of a VMAX left from a move or a
+    /// stop. Returns `Error::InvalidValue` for `PositioningMode`.
+    pub fn switch_to_velocity(&mut self, mode: RampMode) -> Result<DataPacket, Error<E>> {
+        if mode == RampMode::PositioningMode {
+            return Err(Error::InvalidValue);
+        }
+        self.check_emergency_stop()?;
+        self.check_velocity_mode(mode)?;
+        self.restore_v_max()?;
+        let packet = self.set_rampmode(mode)?;
+        self.status = packet.status;
+        Ok(packet)
+    }
+
+    fn restore_v_max(&mut self) -> Result<(), Error<E>> {
+        if let Some(v_max) = self._v_max_nominal {
+            let mut val = scaled_v_max(v_max, self._speed_override)?.to_be_bytes();
+            self.write_register(Registers::VMAX, &mut val)?;
+        }
+        Ok(())
+    }
+
+    /// read the ramp mode and the ramp parameters last written to the chip, see `RampSnapshot`
+    pub fn read_ramp_snapshot(&mut self) -> Result<RampSnapshot, Error<E>> {
+        let ramp_mode = self.read_rampmode()?;
+        let clock = self._clock;
+        let step_count = self._step_count;
+        let velocity = |raw: Option<u32>| {
+            raw.map(|raw| RampValue {
+                raw,
+                physical: conversions::velocity_from_register(raw as f32, clock) / step_count,
+            })
+        };
+        let acceleration = |raw: Option<u32>| {
+            raw.map(|raw| RampValue {
+                raw,
+                physical: conversions::acceleration_from_register(raw as f32, clock) / step_count,
+            })
+        };
+        let [vstart, a1, v1, amax, vmax, dmax, d1, vstop, tzerowait] = self._ramp_written;
+        Ok(RampSnapshot {
+            ramp_mode,
+            vstart: velocity(vstart),
+            a1: acceleration(a1),
+            v1: velocity(v1),
+            amax: acceleration(amax),
+            vmax: velocity(vmax),
+            dmax: acceleration(dmax),
+            d1: acceleration(d1),
+            vstop: velocity(vstop),
+            // TZEROWAIT counts in units of 512 clock cycles
+            tzerowait: tzerowait.map(|raw| RampValue {
+                raw,
+                physical: raw as f32 * 512.0 / clock,
+            }),
+        })
+    }
+
+    /// read OFFSET_READ register (offset calibration results of phase A and B)
+    pub fn read_offset(&mut self) -> Result<OffsetRead, Error<E>> {
+        let packet = self.read_register(Registers::OFFSET_READ)?;
+        self.status = packet.status;
+        Ok(OffsetRead::from_bytes(packet.data.to_le_bytes()))
     }
 
     /// read TSTEP register
@@ -489,6 +2781,129 @@ where
             .map(|packet| packet.data)
     }
 
+    /// compare the velocity measured by TSTEP with VACTUAL of the ramp generator
+    ///
+    /// TSTEP counts clocks per 1/256 microstep, so at a constant velocity it is
+    /// `2^24 / |VACTUAL| * step_count / 256`. A relative deviation above `tolerance` (e.g. 0.05)
+    /// points to a step count (`step_count()`) not matching CHOPCONF.mres or to a clock frequency
+    /// differing from `clock()`. Check at a constant velocity, TSTEP lags behind during acceleration.
+    pub fn check_tstep(&mut self, tolerance: f32) -> Result<TstepCheck, Error<E>> {
+        let vactual = self.read_register(Registers::VACTUAL)?.data;
+        // sign extend the 24 bit value
+        let vactual = ((vactual << 8) as i32) >> 8;
+        let tstep = self.read_tstep()?;
+        let expected = Some(conversions::tstep_from_register(
+            vactual.unsigned_abs() as f32
+        ))
+        .map(|tstep| tstep * self._step_count / 256.0)
+        .filter(|tstep| tstep.is_finite() && *tstep < 0xFFFFF as f32);
+        let deviation = expected.map(|expected| (tstep as f32 - expected).abs() / expected);
+        Ok(TstepCheck {
+            tstep,
+            vactual,
+            expected,
+            deviation,
+            disagrees: matches!(deviation, Some(deviation) if deviation > tolerance),
+        })
+    }
+
+    /// read all readable registers (see `Registers::READABLE`) and pass them to `f`
+    ///
+    /// Reading does not clear any flags, so this can be used at any time for diagnostics.
+    pub fn dump_registers<F: FnMut(Registers, u32)>(&mut self, mut f: F) -> Result<(), Error<E>> {
+        for register in Registers::READABLE {
+            if !CHIP.has_register(register.addr()) {
+                continue;
+            }
+            let packet = self.read_register(register)?;
+            self.status = packet.status;
+            f(register, packet.data);
+        }
+        Ok(())
+    }
+
+    /// read MSCNT register (actual position in the microstep table, 0 to 1023)
+    pub fn read_mscnt(&mut self) -> Result<u16, Error<E>> {
+        let packet = self.read_register(Registers::MSCNT)?;
+        self.status = packet.status;
+        Ok((packet.data & 0x3FF) as u16)
+    }
+
+    /// read MSCURACT register (actual microstep currents of phase A and B)
+    pub fn read_mscuract(&mut self) -> Result<MsCurAct, Error<E>> {
+        let packet = self.read_register(Registers::MSCURACT)?;
+        self.status = packet.status;
+        Ok(MsCurAct::from_bytes(packet.data.to_le_bytes()))
+    }
+
+    /// microstep table in use, the power-on table updated by the writes to MSLUT, MSLUTSEL and
+    /// MSLUTSTART
+    pub fn microstep_table(&self) -> &MicrostepTable {
+        &self._microstep_table
+    }
+
+    /// read MSCNT and MSCURACT and compare the currents with the microstep table
+    ///
+    /// A mismatch points to a wrongly programmed microstep table. As MSCNT and MSCURACT are read
+    /// one after the other, the motor has to be at standstill.
+    pub fn read_electrical_state(&mut self) -> Result<ElectricalState, Error<E>> {
+        let mscnt = self.read_mscnt()?;
+        let mscuract = self.read_mscuract()?;
+        Ok(ElectricalState {
+            mscnt,
+            angle: microstep::electrical_angle(mscnt),
+            expected: self._microstep_table.currents(mscnt),
+            actual: (mscuract.cur_a_signed(), mscuract.cur_b_signed()),
+        })
+    }
+
+    /// read PWM_SCALE register
+    pub fn read_pwm_scale(&mut self) -> Result<PwmScale, Error<E>> {
+        let packet = self.read_register(Registers::PWM_SCALE)?;
+        self.status = packet.status;
+        Ok(PwmScale::from_bytes(packet.data.to_le_bytes()))
+    }
+
+    /// read PWM_AUTO register
+    pub fn read_pwm_auto(&mut self) -> Result<PwmAuto, Error<E>> {
+        let packet = self.read_register(Registers::PWM_AUTO)?;
+        self.status = packet.status;
+        Ok(PwmAuto::from_bytes(packet.data.to_le_bytes()))
+    }
+
+    /// read OTP_READ register (factory programmed reset defaults)
+    pub fn read_otp(&mut self) -> Result<OtpRead, Error<E>> {
+        let packet = self.read_register(Registers::OTP_READ)?;
+        self.status = packet.status;
+        Ok(OtpRead::from_bytes(packet.data.to_le_bytes()))
+    }
+
+    /// read FACTORY_CONF register
+    pub fn read_factory_conf(&mut self) -> Result<FactoryConf, Error<E>> {
+        let packet = self.read_register(Registers::FACTORY_CONF)?;
+        self.status = packet.status;
+        Ok(FactoryConf::from_bytes(packet.data.to_le_bytes()))
+    }
+
+    /// set the internal clock trim FCLKTRIM (0 to 31) and the clock frequency it results in
+    ///
+    /// The trim characteristic is not specified, so `frequency` in Hz has to be measured for the
+    /// trim value, e.g. from the step rate at a known VACTUAL. It replaces the frequency used for
+    /// all time and velocity conversions. The factory trim value (see `read_otp()`) is restored at
+    /// every reset. Returns `Error::NotConfigured` with an external clock.
+    pub fn set_clock_trim(&mut self, trim: u8, frequency: f32) -> Result<DataPacket, Error<E>> {
+        if trim > 31 || !(frequency.is_finite() && frequency > 0.0) {
+            return Err(Error::InvalidValue);
+        }
+        if self._clock_source != ClockSource::Internal {
+            return Err(Error::NotConfigured);
+        }
+        let mut value = swap_bytes(FactoryConf::new().with_fclktrim(trim).into_bytes());
+        let packet = self.write_register(Registers::FACTORY_CONF, &mut value)?;
+        self._clock = frequency;
+        Ok(packet)
+    }
+
     /// read DRV_STATUS register
     pub fn read_drv_status(&mut self) -> Result<DrvStatus, Error<E>> {
         let packet = self.read_register(Registers::DRV_STATUS)?;
@@ -504,6 +2919,54 @@ where
         Ok(GStat::from_bytes(packet.data.to_le_bytes()))
     }
 
+    /// check GSTAT for a charge pump undervoltage, returns `Error::ChargePumpUndervoltage` if set
+    pub fn check_charge_pump(&mut self) -> Result<(), Error<E>> {
+        if self.read_gstat()?.uv_cp() {
+            return Err(Error::ChargePumpUndervoltage);
+        }
+        Ok(())
+    }
+
+    /// recover from a charge pump undervoltage
+    ///
+    /// Waits `settle_ms` for the supply voltage to stabilize, clears GSTAT and writes the shadow
+    /// configuration again (see `update_config()`). Returns `Error::ChargePumpUndervoltage` if the
+    /// undervoltage persists.
+    pub fn recover_charge_pump<D: DelayMs<u32>>(
+        &mut self,
+        delay: &mut D,
+        settle_ms: u32,
+    ) -> Result<DataPacket, Error<E>> {
+        delay.delay_ms(settle_ms);
+        self.clear_g_stat()?;
+        self.check_charge_pump()?;
+        self.update_config()
+    }
+
+    /// read DRV_STATUS, GSTAT and RAMP_STAT
+    pub fn read_fault_report(&mut self) -> Result<FaultReport, Error<E>> {
+        Ok(FaultReport {
+            drv_status: self.read_drv_status()?,
+            gstat: self.read_gstat()?,
+            ramp_stat: self.read_ramp_status()?,
+        })
+    }
+
+    /// read DRV_STATUS and GSTAT and return `Error::DriverFault` if a fault is set
+    pub fn check_faults(&mut self) -> Result<(), Error<E>> {
+        let flags = FaultFlags::from_registers(self.read_drv_status()?, self.read_gstat()?);
+        if !flags.is_empty() {
+            return Err(Error::DriverFault(flags));
+        }
+        Ok(())
+    }
+
+    /// read the fault flags and write them as a compact text report to `w`
+    pub fn format_faults(&mut self, w: &mut impl fmt::Write) -> Result<(), Error<E>> {
+        let report = self.read_fault_report()?;
+        write!(w, "{}", report).map_err(|_| Error::Format)
+    }
+
     /// read GCONF register
     pub fn read_gconf(&mut self) -> Result<GConf, Error<E>> {
         let packet = self.read_register(Registers::GCONF)?;
@@ -511,6 +2974,47 @@ where
         Ok(GConf::from_bytes(packet.data.to_le_bytes()))
     }
 
+    /// read IOIN register
+    pub fn read_ioin(&mut self) -> Result<IoIn, Error<E>> {
+        let packet = self.read_register(Registers::IOIN)?;
+        self.status = packet.status;
+        Ok(IoIn::from_bytes(packet.data.to_le_bytes()))
+    }
+
+    /// check if the SD_MODE pin is strapped high (motion from external STEP/DIR signals)
+    pub fn sd_mode_is_strapped(&mut self) -> Result<bool, Error<E>> {
+        self.read_ioin().map(|ioin| ioin.sd_mode())
+    }
+
+    /// configure the driver for motion from external STEP/DIR signals
+    ///
+    /// Verifies the SD_MODE strapping, parks the internal ramp generator and sets DEDGE (step on
+    /// both edges) and INTPOL (interpolation to 256 microsteps) in CHOPCONF.
+    pub fn enter_step_dir_mode(
+        &mut self,
+        double_edge: bool,
+        interpolation: bool,
+    ) -> Result<DataPacket, Error<E>> {
+        if !self.sd_mode_is_strapped()? {
+            return Err(Error::SdMode);
+        }
+        self.disable_ramp_generator()?;
+        self.chop_conf.set_dedge(double_edge);
+        self.chop_conf.set_intpol(interpolation);
+        self.update_chop_conf()
+    }
+
+    /// park the internal ramp generator (hold mode, VMAX = 0 and XTARGET = XACTUAL)
+    pub fn disable_ramp_generator(&mut self) -> Result<DataPacket, Error<E>> {
+        self.set_rampmode(RampMode::HoldMode)?;
+        self.set_vmax(0)?;
+        self.v_max = 0.0;
+        let mut position = self.read_register(Registers::XACTUAL)?.data.to_be_bytes();
+        let packet = self.write_register(Registers::XTARGET, &mut position)?;
+        self.status = packet.status;
+        Ok(packet)
+    }
+
     /// read RAMP_STAT register
     pub fn read_ramp_status(&mut self) -> Result<RampStat, Error<E>> {
         let packet = self.read_register(Registers::RAMP_STAT)?;
@@ -525,8 +3029,56 @@ where
         Ok(EncStatus::from_bytes(packet.data.to_le_bytes()))
     }
 
+    /// read RAMP_STAT and DRV_STATUS and return the events raised since the last call
+    ///
+    /// Level based flags are only reported once when they become active.
+    pub fn status_events(&mut self) -> Result<StatusEvents, Error<E>> {
+        let ramp_stat = self.read_ramp_status()?;
+        let drv_status = self.read_drv_status()?;
+        let mask = status_mask(self.status, ramp_stat, drv_status);
+        let raised = mask & !self._status_mask;
+        self._status_mask = mask;
+        Ok(StatusEvents::new(raised))
+    }
+
+    /// register a function that is called by `poll_stall()` for every new stall event
+    pub fn set_stall_callback(&mut self, callback: Option<fn(StallEvent)>) {
+        self._stall_callback = callback;
+    }
+
+    /// poll for a new stall event
+    ///
+    /// A latched stop by stallGuard (event_stop_sg) is reported once until the event flag is
+    /// cleared, a stallGuard threshold crossing once until DRV_STATUS.stallguard drops again.
+    pub fn poll_stall(&mut self) -> Result<Option<StallEvent>, Error<E>> {
+        let stopped = self.read_ramp_status()?.event_stop_sg();
+        let drv_status = self.read_drv_status()?;
+        let threshold = drv_status.stallguard();
+        let kind = if stopped && !self._stall_stopped {
+            Some(StallKind::Stopped)
+        } else if threshold && !self._stall_threshold && !stopped {
+            Some(StallKind::Threshold)
+        } else {
+            None
+        };
+        self._stall_stopped = stopped;
+        self._stall_threshold = threshold;
+        let event = kind.map(|kind| StallEvent {
+            kind,
+            sg_result: drv_status.sg_result(),
+        });
+        if event.is_some() {
+            self._counters.stall_events = self._counters.stall_events.wrapping_add(1);
+        }
+        if let (Some(event), Some(callback)) = (event, self._stall_callback) {
+            callback(event);
+        }
+        Ok(event)
+    }
+
     /// set the position to 0 / home
     pub fn set_home(&mut self) -> Result<DataPacket, Error<E>> {
+        self.reset_backlash();
         let mut val = 0_u32.to_be_bytes();
         self.write_register(Registers::XACTUAL, &mut val)?;
         let packet = self.write_register(Registers::XTARGET, &mut val)?;
@@ -547,23 +3099,254 @@ where
         Ok(packet)
     }
 
+    /// stop as fast as possible and reject motion commands until `clear_emergency_stop()`
+    ///
+    /// Switches to velocity mode with VMAX = 0 and the maximum AMAX, which stops faster than the
+    /// configured deceleration. With `disable_stage` the power stage is also disabled through the
+    /// EN pin and the motor coasts. The latch is set before any bus access, so it also holds if
+    /// the SPI transfer fails. The motion state before the stop is saved for
+    /// `recover_from_emergency_stop()`.
+    pub fn emergency_stop(&mut self, disable_stage: bool) -> Result<DataPacket, Error<E>> {
+        self._emergency_stop = true;
+        if disable_stage {
+            self.disable()?;
+        }
+        self.write_register(Registers::AMAX, &mut 0xFFFF_u32.to_be_bytes())?;
+        self.write_register(Registers::VMAX, &mut 0_u32.to_be_bytes())?;
+        if self._emergency_stop_state.is_none() {
+            let ramp_mode = self.read_rampmode()?;
+            let target = self.read_register(Registers::XTARGET)?.data as i32;
+            self._emergency_stop_state = Some(EmergencyStopState {
+                ramp_mode,
+                target,
+                v_max: self.v_max,
+            });
+        }
+        self.v_max = 0.0;
+        let mut mode = (RampMode::VelocityModePos as u32).to_be_bytes();
+        let packet = self.write_register(Registers::RAMPMODE, &mut mode)?;
+        self.status = packet.status;
+        Ok(packet)
+    }
+
+    /// check if an emergency stop is latched
+    pub fn emergency_stop_is_active(&self) -> bool {
+        self._emergency_stop
+    }
+
+    /// re-arm the driver after an emergency stop, the motor has to be at standstill
+    ///
+    /// The ramp registers and the EN pin are left as `emergency_stop()` set them, the next motion
+    /// command has to configure the mode, velocity and acceleration again.
+    pub fn clear_emergency_stop(&mut self) -> Result<(), Error<E>> {
+        if !self.is_at_standstill()? {
+            return Err(Error::NotAtStandstill);
+        }
+        self._emergency_stop = false;
+        Ok(())
+    }
+
+    /// motion state saved by the last `emergency_stop()`, until it is recovered
+    pub fn emergency_stop_state(&self) -> Option<EmergencyStopState> {
+        self._emergency_stop_state
+    }
+
+    /// resume operation after an emergency stop without re-homing, returns the actual position
+    ///
+    /// The emergency stop has to be cleared with `clear_emergency_stop()` first. XTARGET is set to
+    /// XACTUAL and the driver is switched to positioning mode, so it holds the position where it
+    /// stopped. The run current, VMAX and AMAX of before the stop are restored and the driver is
+    /// enabled. With `resume` the axis continues to the target (positioning mode) or with the
+    /// velocity (velocity modes) it had before the stop. Resuming a velocity mode with soft limits
+    /// returns `Error::SoftLimit` before anything is written.
+    ///
+    /// The emergency stop leaves AMAX at the maximum, so `Error::NotConfigured` is returned before
+    /// anything is written if AMAX was not set with `set_amax()`, which can also be called after
+    /// the stop.
+    pub fn recover_from_emergency_stop(&mut self, resume: bool) -> Result<f32, Error<E>> {
+        self.check_emergency_stop()?;
+        let state = self._emergency_stop_state.ok_or(Error::NotConfigured)?;
+        let a_max = self._a_max.ok_or(Error::NotConfigured)?;
+        if resume {
+            self.check_velocity_mode(state.ramp_mode)?;
+        }
+        let mut actual = self.read_register(Registers::XACTUAL)?.data.to_be_bytes();
+        self.write_register(Registers::XTARGET, &mut actual)?;
+        self.set_rampmode(RampMode::PositioningMode)?;
+        self.write_register(Registers::AMAX, &mut a_max.to_be_bytes())?;
+        self.update_ihold_irun()?;
+        self._deep_hold_active = false;
+        self.set_velocity(state.v_max)?;
+        self.enable()?;
+        self._emergency_stop_state = None;
+        if resume {
+            match state.ramp_mode {
+                RampMode::PositioningMode => {
+                    self.arm_target_reached()?;
+                    self.write_register(Registers::XTARGET, &mut state.target.to_be_bytes())?;
+                }
+                mode => {
+                    self.set_rampmode(mode)?;
+                }
+            }
+        }
+        Ok(i32::from_be_bytes(actual) as f32 / self._step_count)
+    }
+
+    fn check_emergency_stop(&self) -> Result<(), Error<E>> {
+        if self._emergency_stop {
+            Err(Error::EmergencyStop)
+        } else {
+            Ok(())
+        }
+    }
+
     /// check if the motor is moving
     pub fn is_moving(&mut self) -> Result<bool, Error<E>> {
         self.read_drv_status().map(|packet| !packet.standstill())
     }
 
+    /// check if the motor is at standstill (RAMP_STAT.vzero and DRV_STATUS.stst)
+    pub fn is_at_standstill(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read_ramp_status()?.vzero() && self.read_drv_status()?.standstill())
+    }
+
+    /// poll `condition` until it is true, returns `Error::Timeout` once the deadline has expired
+    pub fn wait_until<F>(
+        &mut self,
+        deadline: &mut impl Deadline,
+        mut condition: F,
+    ) -> Result<(), Error<E>>
+    where
+        F: FnMut(&mut Self) -> Result<bool, Error<E>>,
+    {
+        loop {
+            if condition(self)? {
+                return Ok(());
+            }
+            if deadline.expired() {
+                return Err(Error::Timeout);
+            }
+            deadline.wait();
+        }
+    }
+
+    /// wait until the motor is at standstill (see `is_at_standstill()`)
+    ///
+    /// Returns `Error::Timeout` if the motor is still moving when the deadline expires.
+    pub fn wait_for_standstill(&mut self, deadline: &mut impl Deadline) -> Result<(), Error<E>> {
+        self.wait_until(deadline, |driver| driver.is_at_standstill())
+    }
+
+    /// async variant of `wait_until()`, awaits the embassy-time timer between the polls
+    #[cfg(feature = "embassy")]
+    pub async fn wait_until_async<F>(
+        &mut self,
+        deadline: &TimerDeadline,
+        mut condition: F,
+    ) -> Result<(), Error<E>>
+    where
+        F: FnMut(&mut Self) -> Result<bool, Error<E>>,
+    {
+        loop {
+            if condition(self)? {
+                return Ok(());
+            }
+            if deadline.expired() {
+                return Err(Error::Timeout);
+            }
+            deadline.wait().await;
+        }
+    }
+
+    /// async variant of `wait_for_standstill()`
+    #[cfg(feature = "embassy")]
+    pub async fn wait_for_standstill_async(
+        &mut self,
+        deadline: &TimerDeadline,
+    ) -> Result<(), Error<E>> {
+        self.wait_until_async(deadline, |driver| driver.is_at_standstill())
+            .await
+    }
+
+    /// wait until the last commanded move has completed (see `target_reached()`)
+    ///
+    /// Returns `Error::Timeout` if the move has not completed when the deadline expires.
+    #[cfg(feature = "embassy")]
+    pub async fn wait_for_target_reached_async(
+        &mut self,
+        deadline: &TimerDeadline,
+    ) -> Result<(), Error<E>> {
+        self.wait_until_async(deadline, |driver| driver.target_reached())
+            .await
+    }
+
     /// check if the motor has reached the target position
     pub fn position_is_reached(&mut self) -> Result<bool, Error<E>> {
         self.read_ramp_status()
             .map(|packet| packet.position_reached())
     }
 
+    /// arm the one-shot target reached detection by clearing event_pos_reached
+    ///
+    /// Called by `move_to()`, call it manually before moves commanded by writing XTARGET directly.
+    pub fn arm_target_reached(&mut self) -> Result<DataPacket, Error<E>> {
+        let packet = self.clear_ramp_event(RampEvent::PosReached)?;
+        self._move_armed = true;
+        self._move_at_target = false;
+        Ok(packet)
+    }
+
+    /// check if the last commanded move has completed
+    ///
+    /// Unlike `position_is_reached()`, this reports true only once per armed move, based on the
+    /// latched event_pos_reached flag. A move to the position the motor already stands at does not
+    /// raise the event, it is reported as completed by the first call. Restores the defaults after a
+    /// `move_to_with()`.
+    pub fn target_reached(&mut self) -> Result<bool, Error<E>> {
+        if !self._move_armed {
+            return Ok(false);
+        }
+        if !self._move_at_target {
+            if !self.read_ramp_status()?.event_pos_reached() {
+                return Ok(false);
+            }
+            self.clear_ramp_event(RampEvent::PosReached)?;
+        }
+        self._move_armed = false;
+        self._move_at_target = false;
+        self.restore_move_defaults()?;
+        Ok(true)
+    }
+
     /// check if the motor has reached the constant velocity
     pub fn velocity_is_reached(&mut self) -> Result<bool, Error<E>> {
         self.read_ramp_status()
             .map(|packet| packet.velocity_reached())
     }
 
+    /// wait until VACTUAL has reached VMAX (RAMP_STAT.velocity_reached)
+    ///
+    /// Returns the actual velocity (VACTUAL) at completion or `Error::Timeout` when the deadline expires.
+    pub fn wait_for_velocity_reached(
+        &mut self,
+        deadline: &mut impl Deadline,
+    ) -> Result<f32, Error<E>> {
+        self.wait_until(deadline, |driver| driver.velocity_is_reached())?;
+        self.get_velocity()
+    }
+
+    /// async variant of `wait_for_velocity_reached()`
+    #[cfg(feature = "embassy")]
+    pub async fn wait_for_velocity_reached_async(
+        &mut self,
+        deadline: &TimerDeadline,
+    ) -> Result<f32, Error<E>> {
+        self.wait_until_async(deadline, |driver| driver.velocity_is_reached())
+            .await?;
+        self.get_velocity()
+    }
+
     /// check if motor is at right limit
     pub fn is_at_limit_r(&mut self) -> Result<bool, Error<E>> {
         self.read_ramp_status().map(|packet| packet.status_stop_r())
@@ -575,40 +3358,317 @@ where
     }
 
     /// set the max velocity (VMAX)
+    ///
+    /// VMAX is scaled by the speed override, see `set_speed_override_percent()`.
     pub fn set_velocity(&mut self, velocity: f32) -> Result<DataPacket, Error<E>> {
+        self.check_emergency_stop()?;
+        let v_max = self.speed_from_hz(velocity)?;
+        let scaled = scaled_v_max(v_max, self._speed_override)?;
+        self.restore_run_current()?;
         self.v_max = velocity;
-        let v_max = self.speed_from_hz(velocity);
-        let mut val = v_max.to_be_bytes();
+        self._v_max_nominal = Some(v_max);
+        let mut val = scaled.to_be_bytes();
         let packet = self.write_register(Registers::VMAX, &mut val)?;
         self.status = packet.status;
         Ok(packet)
     }
 
     /// set the max velocity (VMAX)
+    ///
+    /// VMAX is scaled by the speed override, see `set_speed_override_percent()`.
     pub fn set_velocity_raw(&mut self, velocity: u32) -> Result<DataPacket, Error<E>> {
-        self.v_max = velocity as f32 / self._step_count * (self._clock / 16_777_216.0);
-        let mut val = velocity.to_be_bytes();
+        self.check_emergency_stop()?;
+        checked_range(velocity, 0, VMAX_LIMIT)?;
+        let scaled = scaled_v_max(velocity, self._speed_override)?;
+        self.restore_run_current()?;
+        self.v_max =
+            conversions::velocity_from_register(velocity as f32, self._clock) / self._step_count;
+        self._v_max_nominal = Some(velocity);
+        let mut val = scaled.to_be_bytes();
         let packet = self.write_register(Registers::VMAX, &mut val)?;
         self.status = packet.status;
         Ok(packet)
     }
 
-    /// set the max acceleration (AMAX, DMAX, A1, D1)
+    /// scale the max velocity of the current and all following moves to `percent`
+    ///
+    /// Rewrites VMAX from the nominal velocity of the last `set_velocity()` or
+    /// `set_velocity_raw()`, the ramp generator adapts the velocity with the configured
+    /// acceleration. 0 % stops the motor like a feed hold, values above 100 % must not exceed the
+    /// VMAX limit.
+    pub fn set_speed_override_percent(&mut self, percent: u16) -> Result<(), Error<E>> {
+        if let Some(v_max) = self._v_max_nominal {
+            let mut val = scaled_v_max(v_max, percent)?.to_be_bytes();
+            self.write_register(Registers::VMAX, &mut val)?;
+        }
+        self._speed_override = percent;
+        Ok(())
+    }
+
+    /// get the speed override in percent
+    pub fn get_speed_override_percent(&self) -> u16 {
+        self._speed_override
+    }
+
+    /// set the max acceleration (AMAX, A1)
+    ///
+    /// Until `set_deceleration()` is called, the deceleration (DMAX, D1) is set to the same value.
     pub fn set_acceleration(&mut self, acceleration: f32) -> Result<DataPacket, Error<E>> {
-        let a_max = self.accel_from_hz(acceleration);
+        let a_max = checked_range(self.accel_from_hz(acceleration)?, 1, 0xFFFF)?;
         let mut val = a_max.to_be_bytes();
         self.write_register(Registers::AMAX, &mut val)?;
+        self._a_max = Some(a_max);
+        let packet = self.write_register(Registers::A1, &mut val)?;
+        self.status = packet.status;
+        if !self._deceleration_set {
+            self.write_deceleration(a_max)?;
+        }
+        Ok(packet)
+    }
+
+    /// set the max deceleration (DMAX, D1)
+    pub fn set_deceleration(&mut self, deceleration: f32) -> Result<DataPacket, Error<E>> {
+        // D1 must not be 0 in positioning mode
+        let d_max = checked_range(self.accel_from_hz(deceleration)?, 1, 0xFFFF)?;
+        let packet = self.write_deceleration(d_max)?;
+        self._deceleration_set = true;
+        Ok(packet)
+    }
+
+    fn write_deceleration(&mut self, d_max: u32) -> Result<DataPacket, Error<E>> {
+        let mut val = d_max.to_be_bytes();
         self.write_register(Registers::DMAX, &mut val)?;
-        self.write_register(Registers::A1, &mut val)?;
         let packet = self.write_register(Registers::D1, &mut val)?;
         self.status = packet.status;
         Ok(packet)
     }
 
+    /// write all ramp registers of `profile`
+    pub fn set_ramp_profile(&mut self, profile: &RampProfile) -> Result<DataPacket, Error<E>> {
+        if profile.vstop < profile.vstart {
+            return Err(Error::InvalidValue);
+        }
+        self.set_vstart(profile.vstart)?;
+        self.set_a1(profile.a1)?;
+        self.set_v1(profile.v1)?;
+        self.set_amax(profile.amax)?;
+        self.set_dmax(profile.dmax)?;
+        self.set_d1(profile.d1)?;
+        self.set_vstop(profile.vstop)?;
+        self._deceleration_set = true;
+        self.set_velocity_raw(profile.vmax)
+    }
+
+    /// set a two phase ramp from the max velocity and acceleration, see `RampProfile::two_phase()`
+    ///
+    /// Below half the velocity, the acceleration and deceleration are `start_boost` times higher.
+    pub fn shape_ramp(
+        &mut self,
+        velocity: f32,
+        acceleration: f32,
+        start_boost: f32,
+    ) -> Result<DataPacket, Error<E>> {
+        let v_max = self.speed_from_hz(velocity)?;
+        let a_max = checked_range(self.accel_from_hz(acceleration)?, 1, 0xFFFF)?;
+        self.set_ramp_profile(&RampProfile::two_phase(v_max, a_max, start_boost))
+    }
+
+    /// set an S-curve like ramp from the max velocity and acceleration, see `RampProfile::s_curve()`
+    ///
+    /// `smoothness` from 0 (linear ramp) to 1 (softest start and stop).
+    pub fn set_s_curve(
+        &mut self,
+        velocity: f32,
+        acceleration: f32,
+        smoothness: f32,
+    ) -> Result<DataPacket, Error<E>> {
+        let v_max = self.speed_from_hz(velocity)?;
+        let a_max = checked_range(self.accel_from_hz(acceleration)?, 1, 0xFFFF)?;
+        self.set_ramp_profile(&RampProfile::s_curve(v_max, a_max, smoothness))
+    }
+
+    /// set the backlash distance compensated by `move_to()`, 0 disables the compensation
+    ///
+    /// On every reversal of the move direction, the axis travels `distance` further to take up the
+    /// play. The compensation is kept as an offset between the chip position and the position
+    /// reported by `get_position()` and `get_target()`. The first move after this call only sets
+    /// the reference direction.
+    pub fn set_backlash(&mut self, distance: f32) -> Result<(), Error<E>> {
+        if distance < 0.0 {
+            return Err(Error::InvalidValue);
+        }
+        self._backlash = self.steps_from_position(distance)?;
+        self.reset_backlash();
+        Ok(())
+    }
+
+    /// get the backlash distance
+    pub fn get_backlash(&self) -> f32 {
+        self._backlash as f32 / self._step_count
+    }
+
+    fn reset_backlash(&mut self) {
+        self._backlash_offset = 0;
+        self._backlash_positive = None;
+        self._backlash_target = None;
+    }
+
+    /// chip target for a move to `target` (both in microsteps) with backlash compensation
+    fn compensate_backlash(&mut self, target: i32) -> Result<i32, Error<E>> {
+        if self._backlash == 0 {
+            return Ok(target);
+        }
+        let previous = match self._backlash_target {
+            Some(previous) => previous,
+            None => (self.read_register(Registers::XACTUAL)?.data as i32)
+                .wrapping_sub(self._backlash_offset),
+        };
+        let mut offset = self._backlash_offset;
+        let mut positive = self._backlash_positive;
+        if target != previous {
+            let forward = target > previous;
+            if positive == Some(!forward) {
+                offset += if forward {
+                    self._backlash
+                } else {
+                    -self._backlash
+                };
+            }
+            positive = Some(forward);
+        }
+        let compensated = target.checked_add(offset).ok_or(Error::InvalidValue)?;
+        self._backlash_offset = offset;
+        self._backlash_positive = positive;
+        self._backlash_target = Some(target);
+        Ok(compensated)
+    }
+
+    /// restrict the travel to the range from `min` to `max`
+    ///
+    /// `move_to()` refuses targets outside of the range and `set_rampmode()` the velocity modes with
+    /// `Error::SoftLimit`. `run_to_soft_limit()` moves to the limit in the direction of travel in
+    /// positioning mode, where the axis stops with the normal deceleration ramp. The limits apply to the position reported by
+    /// `get_position()`, they do not replace the stop switches.
+    pub fn set_soft_limits(&mut self, min: f32, max: f32) -> Result<(), Error<E>> {
+        let min = self.steps_from_position(min)?;
+        let max = self.steps_from_position(max)?;
+        if min > max {
+            return Err(Error::InvalidValue);
+        }
+        self._soft_limits = Some((min, max));
+        Ok(())
+    }
+
+    /// remove the soft limits
+    pub fn clear_soft_limits(&mut self) {
+        self._soft_limits = None;
+    }
+
+    /// get the soft limits (min, max)
+    pub fn get_soft_limits(&self) -> Option<(f32, f32)> {
+        self._soft_limits
+            .map(|(min, max)| (min as f32 / self._step_count, max as f32 / self._step_count))
+    }
+
+    /// check a target in microsteps against the soft limits
+    fn check_soft_limits(&self, target: i32) -> Result<(), Error<E>> {
+        match self._soft_limits {
+            Some((min, max)) if target < min || target > max => Err(Error::SoftLimit),
+            _ => Ok(()),
+        }
+    }
+
+    /// refuse the velocity modes, which are not bounded by the soft limits
+    fn check_velocity_mode(&self, mode: RampMode) -> Result<(), Error<E>> {
+        match (mode, self._soft_limits) {
+            (RampMode::VelocityModePos | RampMode::VelocityModeNeg, Some(_)) => {
+                Err(Error::SoftLimit)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// run in the direction of the velocity mode `mode` up to the soft limit
+    ///
+    /// The move to the limit is done in positioning mode with the velocity of the last
+    /// `set_velocity()`, so `target_reached()` reports the arrival at the limit. Returns
+    /// `Error::NotConfigured` without soft limits and `Error::InvalidValue` for the other modes.
+    pub fn run_to_soft_limit(&mut self, mode: RampMode) -> Result<DataPacket, Error<E>> {
+        self.check_emergency_stop()?;
+        let (min, max) = self._soft_limits.ok_or(Error::NotConfigured)?;
+        let limit = match mode {
+            RampMode::VelocityModePos => max,
+            RampMode::VelocityModeNeg => min,
+            _ => return Err(Error::InvalidValue),
+        };
+        self.restore_move_defaults()?;
+        self.restore_v_max()?;
+        self.start_move(limit)?;
+        self.set_rampmode(RampMode::PositioningMode)
+    }
+
     /// move to a specific location
+    ///
+    /// Applies the backlash compensation, see `set_backlash()`, and checks the soft limits, see
+    /// `set_soft_limits()`.
     pub fn move_to(&mut self, target: f32) -> Result<DataPacket, Error<E>> {
+        self.check_emergency_stop()?;
+        let target = self.steps_from_position(target)?;
+        self.check_soft_limits(target)?;
+        self.restore_move_defaults()?;
+        self.start_move(target)
+    }
+
+    /// move to a specific location with a max velocity and acceleration for this move only
+    ///
+    /// VMAX and AMAX/A1 are restored when `target_reached()` reports the completion of the move
+    /// or with the next `move_to()`, if they were set through the driver before. The deceleration
+    /// is not changed.
+    pub fn move_to_with(
+        &mut self,
+        target: f32,
+        velocity: f32,
+        acceleration: f32,
+    ) -> Result<DataPacket, Error<E>> {
+        self.check_emergency_stop()?;
+        let target = self.steps_from_position(target)?;
+        self.check_soft_limits(target)?;
+        let v_max = self.speed_from_hz(velocity)?;
+        let a_max = checked_range(self.accel_from_hz(acceleration)?, 1, 0xFFFF)?;
+        let defaults = self
+            ._move_defaults
+            .take()
+            .unwrap_or((self._v_max_nominal, self._a_max));
+        self._move_defaults = Some(defaults);
+        self.set_velocity_raw(v_max)?;
+        self.write_register(Registers::AMAX, &mut a_max.to_be_bytes())?;
+        self.write_register(Registers::A1, &mut a_max.to_be_bytes())?;
+        self.start_move(target)
+    }
+
+    fn restore_move_defaults(&mut self) -> Result<(), Error<E>> {
+        if let Some((v_max, a_max)) = self._move_defaults.take() {
+            if let Some(v_max) = v_max {
+                self.set_velocity_raw(v_max)?;
+            }
+            if let Some(a_max) = a_max {
+                self.write_register(Registers::AMAX, &mut a_max.to_be_bytes())?;
+                self.write_register(Registers::A1, &mut a_max.to_be_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// start a move to `target` in microsteps
+    fn start_move(&mut self, target: i32) -> Result<DataPacket, Error<E>> {
+        let target = self.compensate_backlash(target)?;
         self.enable()?;
-        let target = (target * self._step_count) as i32;
+        self.restore_run_current()?;
+        // with XACTUAL = XTARGET = target the write starts no move and event_pos_reached stays clear
+        let actual = self.read_register(Registers::XACTUAL)?;
+        let at_target = actual.data as i32 == target && actual.status.position_reached();
+        self.arm_target_reached()?;
+        self._move_at_target = at_target;
         let mut val = target.to_be_bytes();
         let packet = self.write_register(Registers::XTARGET, &mut val)?;
         self.status = packet.status;
@@ -621,16 +3681,20 @@ where
             .map(|val| (val.data as i32) as f32 / self._step_count)
     }
 
-    /// get the current position
+    /// get the current position, excluding the backlash offset (see `set_backlash()`)
     pub fn get_position(&mut self) -> Result<f32, Error<E>> {
-        self.read_register(Registers::XACTUAL)
-            .map(|val| (val.data as i32) as f32 / self._step_count)
+        self.read_register(Registers::XACTUAL).map(|val| {
+            (val.data as i32).wrapping_sub(self._backlash_offset) as f32 / self._step_count
+        })
     }
 
     /// set the current position
     pub fn set_position(&mut self, target_signed: i32) -> Result<DataPacket, Error<E>> {
-        let target = target_signed;
-        let mut val = (target * self._step_count as i32).to_be_bytes();
+        let target = target_signed
+            .checked_mul(self._step_count as i32)
+            .ok_or(Error::InvalidValue)?;
+        self.reset_backlash();
+        let mut val = target.to_be_bytes();
         self.write_register(Registers::XACTUAL, &mut val)
     }
 
@@ -655,7 +3719,8 @@ where
 
     /// get the current target position (XTARGET)
     pub fn get_target(&mut self) -> Result<f32, Error<E>> {
-        self.read_register(Registers::XTARGET)
-            .map(|val| (val.data as i32) as f32 / self._step_count)
+        self.read_register(Registers::XTARGET).map(|val| {
+            (val.data as i32).wrapping_sub(self._backlash_offset) as f32 / self._step_count
+        })
     }
 }
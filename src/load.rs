@@ -0,0 +1,233 @@
+//! Software load monitoring and load dependent current control
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+
+use crate::registers::DrvStatus;
+use crate::split::DriverAccess;
+use crate::{Error, Tmc5160};
+
+/// Software load-adaptive run current control
+///
+/// Reads SG_RESULT periodically and adjusts IRUN between `min_run` and `max_run`: a low SG_RESULT
+/// (high load) increases the current, a high SG_RESULT (low load) decreases it. Values between
+/// `sg_low` and `sg_high` form the hysteresis band in which the current is kept.
+/// The current changes by at most one step every `interval_ms`.
+#[derive(Debug, Copy, Clone)]
+pub struct AdaptiveCurrent {
+    /// lowest run current (0 to 31)
+    pub min_run: u8,
+    /// highest run current (0 to 31)
+    pub max_run: u8,
+    /// SG_RESULT below which the current is increased
+    pub sg_low: u16,
+    /// SG_RESULT above which the current is decreased
+    pub sg_high: u16,
+    /// minimum time in ms between two current steps
+    pub interval_ms: u32,
+    last_step: Option<u32>,
+}
+
+impl AdaptiveCurrent {
+    /// create a new adaptive current control
+    pub fn new(min_run: u8, max_run: u8, sg_low: u16, sg_high: u16, interval_ms: u32) -> Self {
+        AdaptiveCurrent {
+            min_run,
+            max_run,
+            sg_low,
+            sg_high,
+            interval_ms,
+            last_step: None,
+        }
+    }
+
+    /// run one iteration of the control loop and return the run current in use
+    ///
+    /// `now_ms` is a monotonic millisecond timestamp of the application. SG_RESULT is only
+    /// evaluated while the motor is moving. The new run current is written to the IHOLD_IRUN
+    /// shadow register and the chip, the hold current is limited to the run current.
+    pub fn update<A, SPI, CS, EN, E>(&mut self, driver: &mut A, now_ms: u32) -> Result<u8, Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        driver.access(|driver| self.update_driver(driver, now_ms))
+    }
+
+    fn update_driver<SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut Tmc5160<SPI, CS, EN>,
+        now_ms: u32,
+    ) -> Result<u8, Error<E>>
+    where
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        if self.min_run > self.max_run || self.max_run > 31 || self.sg_low > self.sg_high {
+            return Err(Error::InvalidValue);
+        }
+        let i_run = driver.ihold_irun.i_run();
+        if let Some(last_step) = self.last_step {
+            if now_ms.wrapping_sub(last_step) < self.interval_ms {
+                return Ok(i_run);
+            }
+        }
+        let status = driver.read_drv_status()?;
+        if status.standstill() {
+            return Ok(i_run);
+        }
+        let sg_result = status.sg_result();
+        let new_run = if sg_result < self.sg_low {
+            (i_run + 1).min(self.max_run)
+        } else if sg_result > self.sg_high {
+            i_run.saturating_sub(1).max(self.min_run)
+        } else {
+            i_run.clamp(self.min_run, self.max_run)
+        };
+        if new_run != i_run {
+            driver.ihold_irun.set_i_run(new_run);
+            driver
+                .ihold_irun
+                .set_i_hold(driver.ihold_irun.i_hold().min(new_run));
+            driver.update_ihold_irun()?;
+            self.last_step = Some(now_ms);
+        }
+        Ok(new_run)
+    }
+}
+
+/// Minimum, maximum and average of a value
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Stat {
+    /// smallest value
+    pub min: u16,
+    /// largest value
+    pub max: u16,
+    /// average value
+    pub average: f32,
+}
+
+/// Load statistics of one window
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LoadStats {
+    /// number of samples in the window
+    pub samples: u32,
+    /// actual motor current scaling (CS_ACTUAL)
+    pub cs_actual: Stat,
+    /// stallGuard result (SG_RESULT), lower values mean higher load
+    pub sg_result: Stat,
+}
+
+/// Accumulator for CS_ACTUAL and SG_RESULT statistics over a window of samples
+///
+/// Samples taken at standstill are ignored, as SG_RESULT is only valid while the motor is moving.
+#[derive(Debug, Copy, Clone)]
+pub struct LoadTracker {
+    window: u32,
+    samples: u32,
+    cs_min: u16,
+    cs_max: u16,
+    cs_sum: u64,
+    sg_min: u16,
+    sg_max: u16,
+    sg_sum: u64,
+    last: Option<LoadStats>,
+}
+
+impl LoadTracker {
+    /// create a new tracker with a window of `window` samples
+    pub fn new(window: u32) -> Self {
+        LoadTracker {
+            window: window.max(1),
+            samples: 0,
+            cs_min: u16::MAX,
+            cs_max: 0,
+            cs_sum: 0,
+            sg_min: u16::MAX,
+            sg_max: 0,
+            sg_sum: 0,
+            last: None,
+        }
+    }
+
+    /// read DRV_STATUS and add it to the statistics, returns the statistics of a completed window
+    pub fn sample<A, SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut A,
+    ) -> Result<Option<LoadStats>, Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        driver.access(|driver| self.sample_driver(driver))
+    }
+
+    fn sample_driver<SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut Tmc5160<SPI, CS, EN>,
+    ) -> Result<Option<LoadStats>, Error<E>>
+    where
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        let status = driver.read_drv_status()?;
+        Ok(self.add(status))
+    }
+
+    /// add an already read DRV_STATUS to the statistics, returns the statistics of a completed window
+    pub fn add(&mut self, status: DrvStatus) -> Option<LoadStats> {
+        if status.standstill() {
+            return None;
+        }
+        let cs_actual = status.cs_actual() as u16;
+        let sg_result = status.sg_result();
+        self.samples += 1;
+        self.cs_min = self.cs_min.min(cs_actual);
+        self.cs_max = self.cs_max.max(cs_actual);
+        self.cs_sum += cs_actual as u64;
+        self.sg_min = self.sg_min.min(sg_result);
+        self.sg_max = self.sg_max.max(sg_result);
+        self.sg_sum += sg_result as u64;
+        if self.samples < self.window {
+            return None;
+        }
+        let stats = self.current();
+        *self = LoadTracker {
+            last: Some(stats),
+            ..LoadTracker::new(self.window)
+        };
+        Some(stats)
+    }
+
+    /// statistics of the running (incomplete) window
+    pub fn current(&self) -> LoadStats {
+        if self.samples == 0 {
+            return LoadStats::default();
+        }
+        LoadStats {
+            samples: self.samples,
+            cs_actual: Stat {
+                min: self.cs_min,
+                max: self.cs_max,
+                average: self.cs_sum as f32 / self.samples as f32,
+            },
+            sg_result: Stat {
+                min: self.sg_min,
+                max: self.sg_max,
+                average: self.sg_sum as f32 / self.samples as f32,
+            },
+        }
+    }
+
+    /// statistics of the last completed window
+    pub fn last(&self) -> Option<LoadStats> {
+        self.last
+    }
+}
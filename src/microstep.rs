@@ -0,0 +1,110 @@
+//! Evaluation of the microstep table (MSLUT, MSLUTSEL, MSLUTSTART)
+//!
+//! The table describes a quarter sine wave of 256 entries by the difference between neighboring
+//! entries: bit `i` of MSLUT selects between `W - 1` and `W` as increment from entry `i` to
+//! `i + 1`, where `W` is the width of the segment (W0 to W3, separated at X1 to X3) containing
+//! `i`. The full wave of 1024 microsteps (MSCNT) is mirrored from the quarter wave, phase B
+//! (cosine) leads phase A (sine) by 256 microsteps.
+use crate::registers::{MsLutSel, MsLutStart};
+
+/// Microstep table of the chip
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MicrostepTable {
+    /// MSLUT[0] to MSLUT[7]
+    pub mslut: [u32; 8],
+    /// MSLUTSEL register
+    pub sel: MsLutSel,
+    /// MSLUTSTART register
+    pub start: MsLutStart,
+}
+
+/// the power-on sine table of the datasheet
+impl Default for MicrostepTable {
+    fn default() -> Self {
+        MicrostepTable {
+            mslut: [
+                0xAAAA_B554,
+                0x4A95_54AA,
+                0x2449_2929,
+                0x1010_4222,
+                0xFBFF_FFFF,
+                0xB5BB_777D,
+                0x4929_5556,
+                0x0040_4222,
+            ],
+            sel: MsLutSel::from_bytes(0xFFFF_8056_u32.to_le_bytes()),
+            start: MsLutStart::from_bytes(0x00F7_0000_u32.to_le_bytes()),
+        }
+    }
+}
+
+impl MicrostepTable {
+    /// value of the quarter wave entry `index` (0 to 256)
+    ///
+    /// Entry 0 is START_SIN, entry 256 is START_SIN90.
+    pub fn entry(&self, index: u16) -> i16 {
+        if index >= 256 {
+            return self.start.start_sin90() as i16;
+        }
+        let sel = &self.sel;
+        let widths = [sel.w0(), sel.w1(), sel.w2(), sel.w3()];
+        let mut value = self.start.start_sin() as i16;
+        for i in 0..index as usize {
+            let segment = match i as u8 {
+                i if i < sel.x1() => 0,
+                i if i < sel.x2() => 1,
+                i if i < sel.x3() => 2,
+                _ => 3,
+            };
+            let bit = (self.mslut[i / 32] >> (i % 32)) & 1;
+            value += widths[segment] as i16 - 1 + bit as i16;
+        }
+        value
+    }
+
+    /// sine wave value at the microstep position `mscnt` (0 to 1023)
+    pub fn sine(&self, mscnt: u16) -> i16 {
+        let mscnt = mscnt & 0x3FF;
+        match mscnt {
+            0..=255 => self.entry(mscnt),
+            256..=511 => self.entry(512 - mscnt),
+            512..=767 => -self.entry(mscnt - 512),
+            _ => -self.entry(1024 - mscnt),
+        }
+    }
+
+    /// expected CUR_A and CUR_B of MSCURACT at the microstep position `mscnt` (0 to 1023)
+    pub fn currents(&self, mscnt: u16) -> (i16, i16) {
+        (self.sine(mscnt), self.sine(mscnt.wrapping_add(256)))
+    }
+}
+
+/// electrical angle in degrees (0 to 360) of the microstep position `mscnt`
+///
+/// ```
+/// use tmc5160::microstep::electrical_angle;
+/// assert_eq!(electrical_angle(256), 90.0);
+/// ```
+pub fn electrical_angle(mscnt: u16) -> f32 {
+    (mscnt & 0x3FF) as f32 * 360.0 / 1024.0
+}
+
+/// Electrical state read by `Tmc5160::read_electrical_state()`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ElectricalState {
+    /// position in the microstep table (MSCNT)
+    pub mscnt: u16,
+    /// electrical angle in degrees
+    pub angle: f32,
+    /// CUR_A and CUR_B expected from the microstep table
+    pub expected: (i16, i16),
+    /// CUR_A and CUR_B reported by MSCURACT
+    pub actual: (i16, i16),
+}
+
+impl ElectricalState {
+    /// check if the reported currents match the microstep table
+    pub fn matches(&self) -> bool {
+        self.expected == self.actual
+    }
+}
@@ -0,0 +1,98 @@
+//! Expectation builders for testing against `embedded-hal-mock`
+//!
+//! Describes the register accesses a piece of motion code is expected to make and turns them into
+//! the SPI and CS transactions of the mocks, including the dummy datagram of every read.
+//!
+//! ```ignore
+//! let mut mocks = Expectations::new()
+//!     .write(Registers::VMAX, 1000)
+//!     .read(Registers::XACTUAL, 42)
+//!     .build();
+//! let mut driver = mocks.driver();
+//! driver.set_velocity_raw(1000)?;
+//! assert_eq!(driver.get_position()?, 42.0 / 256.0);
+//! mocks.done();
+//! ```
+extern crate std;
+
+use std::vec;
+use std::vec::Vec;
+
+use embedded_hal_mock::pin::{Mock as PinMock, State, Transaction as PinTransaction};
+use embedded_hal_mock::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+
+use crate::registers::{Address, Registers};
+use crate::Tmc5160;
+
+/// Builder for the expected datagrams of a driver
+#[derive(Default)]
+pub struct Expectations {
+    spi: Vec<SpiTransaction>,
+    cs: Vec<PinTransaction>,
+    status: u8,
+}
+
+impl Expectations {
+    /// create an empty set of expectations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// SPI status byte returned with the following datagrams
+    pub fn status(mut self, status: u8) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// expect a single datagram with the given reply data
+    pub fn datagram(mut self, datagram: [u8; 5], reply: u32) -> Self {
+        let mut response = vec![self.status];
+        response.extend_from_slice(&reply.to_be_bytes());
+        self.spi
+            .push(SpiTransaction::transfer(datagram.to_vec(), response));
+        self.cs.push(PinTransaction::set(State::Low));
+        self.cs.push(PinTransaction::set(State::High));
+        self
+    }
+
+    /// expect a write of `val` to `reg`
+    pub fn write(self, reg: Registers, val: u32) -> Self {
+        let [a, b, c, d] = val.to_be_bytes();
+        self.datagram([reg.addr() | 0x80, a, b, c, d], 0)
+    }
+
+    /// expect a read of `reg` (the dummy datagram and the actual read), answered with `val`
+    pub fn read(self, reg: Registers, val: u32) -> Self {
+        self.datagram([reg.addr(), 0, 0, 0, 0], 0)
+            .datagram([reg.addr(), 0, 0, 0, 0], val)
+    }
+
+    /// create the mocks
+    pub fn build(self) -> Mocks {
+        Mocks {
+            spi: SpiMock::new(&self.spi),
+            cs: PinMock::new(&self.cs),
+        }
+    }
+}
+
+/// SPI and CS mocks created from `Expectations`
+pub struct Mocks {
+    /// SPI bus mock
+    pub spi: SpiMock,
+    /// CS pin mock
+    pub cs: PinMock,
+}
+
+impl Mocks {
+    /// create a driver on clones of the mocks
+    pub fn driver(&self) -> Tmc5160<SpiMock, PinMock, PinMock> {
+        Tmc5160::new(self.spi.clone(), self.cs.clone())
+    }
+
+    /// check that all expected transactions took place
+    pub fn done(&mut self) {
+        self.spi.done();
+        self.cs.done();
+    }
+}
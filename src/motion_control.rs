@@ -0,0 +1,56 @@
+//! Implementation of the `stepper` crate's motion control trait
+//!
+//! The TMC5160 runs the ramp generator itself, so a motion is started by writing VMAX and XTARGET
+//! in positioning mode and `update()` only has to check whether the target has been reached.
+//! Velocities are given in the same unit as `Tmc5160::set_velocity()`, positions in microsteps.
+//! Like `Tmc5160::move_to_with()`, the velocity only applies to this move and the backlash
+//! compensation is applied to the target.
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+use stepper::traits::MotionControl;
+
+use crate::registers::{RampMode, Registers};
+use crate::{Error, Tmc5160};
+
+impl<SPI, CS, EN, E> MotionControl for Tmc5160<SPI, CS, EN>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin,
+    EN: OutputPin,
+{
+    type Velocity = f32;
+    type Error = Error<E>;
+
+    fn move_to_position(
+        &mut self,
+        max_velocity: Self::Velocity,
+        target_step: i32,
+    ) -> Result<(), Self::Error> {
+        self.check_emergency_stop()?;
+        self.check_soft_limits(target_step)?;
+        let v_max = self.speed_from_hz(max_velocity)?;
+        let defaults = self
+            ._move_defaults
+            .take()
+            .unwrap_or((self._v_max_nominal, None));
+        self._move_defaults = Some(defaults);
+        self.set_rampmode(RampMode::PositioningMode)?;
+        self.set_velocity_raw(v_max)?;
+        self.start_move(target_step)?;
+        Ok(())
+    }
+
+    fn reset_position(&mut self, step: i32) -> Result<(), Self::Error> {
+        // write XTARGET as well, otherwise the ramp generator starts moving back to the old target
+        let mut val = step.to_be_bytes();
+        self.write_register(Registers::XACTUAL, &mut val)?;
+        self.write_register(Registers::XTARGET, &mut val)?;
+        Ok(())
+    }
+
+    fn update(&mut self) -> Result<bool, Self::Error> {
+        self.position_is_reached().map(|reached| !reached)
+    }
+}
@@ -0,0 +1,79 @@
+//! Ramp profiles for the two-phase ramp generator
+//!
+//! The ramp generator accelerates with A1 from VSTART to V1, then with AMAX up to VMAX, and
+//! decelerates with DMAX down to V1, then with D1 to VSTOP. All values are raw register values,
+//! see `conversions` for the scaling.
+
+/// Register values of a complete ramp, written by `Tmc5160::set_ramp_profile()`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RampProfile {
+    /// start velocity (VSTART)
+    pub vstart: u32,
+    /// acceleration between VSTART and V1 (A1)
+    pub a1: u32,
+    /// velocity between the two acceleration phases (V1), 0 disables the first phase
+    pub v1: u32,
+    /// acceleration between V1 and VMAX (AMAX)
+    pub amax: u32,
+    /// maximum velocity (VMAX)
+    pub vmax: u32,
+    /// deceleration between VMAX and V1 (DMAX)
+    pub dmax: u32,
+    /// deceleration between V1 and VSTOP (D1)
+    pub d1: u32,
+    /// stop velocity (VSTOP)
+    pub vstop: u32,
+}
+
+impl RampProfile {
+    /// single phase ramp with the same acceleration and deceleration everywhere
+    pub fn linear(vmax: u32, acceleration: u32) -> Self {
+        RampProfile {
+            vstart: 0,
+            a1: acceleration,
+            v1: 0,
+            amax: acceleration,
+            vmax,
+            dmax: acceleration,
+            d1: acceleration,
+            vstop: 10,
+        }
+    }
+
+    /// two phase ramp with `start_boost` times the acceleration below V1
+    ///
+    /// The torque of a stepper motor decreases with velocity, so the lower half of the velocity
+    /// range (V1 = VMAX / 2) can use a higher acceleration and deceleration (A1 and D1) than the
+    /// upper half (AMAX and DMAX). A `start_boost` of 1 or less results in a linear ramp.
+    pub fn two_phase(vmax: u32, acceleration: u32, start_boost: f32) -> Self {
+        if start_boost <= 1.0 {
+            return Self::linear(vmax, acceleration);
+        }
+        let boosted = ((acceleration as f32 * start_boost) as u32).min(0xFFFF);
+        RampProfile {
+            a1: boosted,
+            v1: vmax / 2,
+            d1: boosted,
+            ..Self::linear(vmax, acceleration)
+        }
+    }
+
+    /// approximation of an S-curve with the two phase ramp
+    ///
+    /// The chip has no jerk limitation, but starting and stopping with a reduced acceleration
+    /// (A1 and D1 below V1) splits the acceleration step into two smaller steps.
+    /// `smoothness` ranges from 0 (linear ramp) to 1 (A1 = D1 = AMAX / 4 up to V1 = VMAX / 2).
+    pub fn s_curve(vmax: u32, acceleration: u32, smoothness: f32) -> Self {
+        if smoothness.is_nan() || smoothness <= 0.0 {
+            return Self::linear(vmax, acceleration);
+        }
+        let smoothness = smoothness.min(1.0);
+        let gentle = ((acceleration as f32 * (1.0 - 0.75 * smoothness)) as u32).max(1);
+        RampProfile {
+            a1: gentle,
+            v1: (vmax as f32 * 0.5 * smoothness) as u32,
+            d1: gentle,
+            ..Self::linear(vmax, acceleration)
+        }
+    }
+}
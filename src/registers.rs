@@ -12,134 +12,398 @@ pub trait Address {
 }
 
 
-/// Register addresses of the TMC5160
-#[derive(Debug, Copy, Clone)]
-#[allow(dead_code, non_camel_case_types)]
-pub enum Registers {
+/// Access type of a register
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Access {
+    /// read only
+    Read,
+    /// write only, reading returns 0
+    Write,
+    /// read and write
+    ReadWrite,
+    /// read, flags are cleared by writing 1
+    ReadClear,
+}
+
+impl Access {
+    /// check if the register can be read
+    pub const fn is_readable(self) -> bool {
+        !matches!(self, Access::Write)
+    }
+
+    /// check if the register can be written
+    pub const fn is_writable(self) -> bool {
+        !matches!(self, Access::Read)
+    }
+}
+
+/// Bit field of a register
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Field {
+    /// name of the field as in the datasheet (lower case)
+    pub name: &'static str,
+    /// position of the lowest bit
+    pub offset: u8,
+    /// number of bits
+    pub width: u8,
+    /// two's complement value
+    pub signed: bool,
+}
+
+impl Field {
+    /// extract the value of the field from a register value
+    pub fn extract(&self, value: u32) -> i64 {
+        let raw = (value as u64 >> self.offset) & ((1_u64 << self.width) - 1);
+        if self.signed && raw & (1 << (self.width - 1)) != 0 {
+            raw as i64 - (1_i64 << self.width)
+        } else {
+            raw as i64
+        }
+    }
+
+    /// mask of the field in the register value
+    pub const fn mask(&self) -> u32 {
+        (((1_u64 << self.width) - 1) as u32) << self.offset
+    }
+}
+
+const fn field(name: &'static str, offset: usize, width: usize, signed: bool) -> Field {
+    Field {
+        name,
+        offset: offset as u8,
+        width: width as u8,
+        signed,
+    }
+}
+
+/// Register with a bitfield type
+///
+/// Implemented by the `register!` declarations, which bind the bitfield struct to its entry in
+/// `Registers` and generate the conversion from and to the 32 bit register value.
+///
+/// ```
+/// use tmc5160::registers::{Access, GConf, Register, Registers};
+/// assert_eq!(GConf::REGISTER, Registers::GCONF);
+/// assert_eq!(GConf::ACCESS, Access::ReadWrite);
+/// assert!(GConf::from_value(0x4).en_pwm_mode());
+/// assert_eq!(GConf::new().with_shaft(true).value(), 0x10);
+/// assert_eq!(GConf::FIELDS[4].name, "shaft");
+/// ```
+pub trait Register: Copy {
+    /// address of the register
+    const REGISTER: Registers;
+    /// access type of the register
+    const ACCESS: Access;
+    /// bit fields of the register, without the reserved bits
+    const FIELDS: &'static [Field];
+
+    /// decode a register value
+    fn from_value(value: u32) -> Self;
+
+    /// encode the register value
+    fn value(self) -> u32;
+}
+
+/// fields of a register in the table: a bitfield struct, or `value`/`signed` and the width
+macro_rules! layout {
+    (value $width:literal) => {
+        &[Field { name: "value", offset: 0, width: $width, signed: false }]
+    };
+    (signed $width:literal) => {
+        &[Field { name: "value", offset: 0, width: $width, signed: true }]
+    };
+    ($layout:ident) => {
+        <$layout as Register>::FIELDS
+    };
+}
+
+/// declare the register table: address, access type and layout of each register
+macro_rules! registers {
+    ($($(#[$meta:meta])* $name:ident = $addr:literal, $access:ident, $kind:ident $($width:literal)?;)*) => {
+        const REGISTER_COUNT: usize = [$(Registers::$name,)*].len();
+
+        /// Register addresses of the TMC5160
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        #[allow(dead_code, non_camel_case_types)]
+        pub enum Registers {
+            $($(#[$meta])* $name = $addr,)*
+        }
+
+        impl Registers {
+            /// all registers in address order
+            pub const ALL: [Registers; REGISTER_COUNT] = [$(Registers::$name,)*];
+
+            /// access type of the register
+            pub const fn access(self) -> Access {
+                match self {
+                    $(Registers::$name => Access::$access,)*
+                }
+            }
+
+            /// name of the register as in the datasheet
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(Registers::$name => stringify!($name),)*
+                }
+            }
+
+            /// bit fields of the register, a value register has a single field named `value`
+            pub const fn fields(self) -> &'static [Field] {
+                match self {
+                    $(Registers::$name => layout!($kind $($width)?),)*
+                }
+            }
+        }
+    };
+}
+
+/// declare the bitfield struct of a register in the table
+///
+/// The fields are collected into `Register::FIELDS`, `#[signed]` marks a two's complement field.
+macro_rules! register {
+    (@field $head:tt [$($struct:tt)*] [$($fields:tt)*] ($offset:expr)
+        #[skip] $field:ident: $ty:ty $(, $($rest:tt)*)?) => {
+        register!(@field $head [$($struct)* #[skip] $field: $ty,] [$($fields)*]
+            ($offset + <$ty as Specifier>::BITS) $($($rest)*)?);
+    };
+    (@field $head:tt [$($struct:tt)*] [$($fields:tt)*] ($offset:expr)
+        $(#[doc = $doc:literal])* #[signed] pub $field:ident: $ty:ty $(, $($rest:tt)*)?) => {
+        register!(@field $head [$($struct)* $(#[doc = $doc])* pub $field: $ty,]
+            [$($fields)* field(stringify!($field), $offset, <$ty as Specifier>::BITS, true),]
+            ($offset + <$ty as Specifier>::BITS) $($($rest)*)?);
+    };
+    (@field $head:tt [$($struct:tt)*] [$($fields:tt)*] ($offset:expr)
+        $(#[doc = $doc:literal])* pub $field:ident: $ty:ty $(, $($rest:tt)*)?) => {
+        register!(@field $head [$($struct)* $(#[doc = $doc])* pub $field: $ty,]
+            [$($fields)* field(stringify!($field), $offset, <$ty as Specifier>::BITS, false),]
+            ($offset + <$ty as Specifier>::BITS) $($($rest)*)?);
+    };
+    (@field [$(#[$meta:meta])* $name:ident @ $reg:ident] [$($struct:tt)*] [$($fields:tt)*]
+        ($offset:expr)) => {
+        #[derive(Clone, Copy)]
+        #[allow(dead_code)]
+        #[bitfield(bits = 32)]
+        #[derive(ToValue, Debug, PartialEq, Eq)]
+        $(#[$meta])*
+        pub struct $name {
+            $($struct)*
+        }
+
+        impl Register for $name {
+            const REGISTER: Registers = Registers::$reg;
+            const ACCESS: Access = Registers::$reg.access();
+            const FIELDS: &'static [Field] = &[$($fields)*];
+
+            fn from_value(value: u32) -> Self {
+                Self::from_bytes(value.to_le_bytes())
+            }
+
+            fn value(self) -> u32 {
+                u32::from_le_bytes(self.into_bytes())
+            }
+        }
+
+        impl Address for $name {
+            fn addr(self) -> u8 {
+                Registers::$reg as u8
+            }
+        }
+    };
+    ($(#[$meta:meta])* $name:ident @ $reg:ident { $($fields:tt)* }) => {
+        register!(@field [$(#[$meta])* $name @ $reg] [] [] (0) $($fields)*);
+    };
+}
+
+registers! {
     /* General configuration registers */
     /// Global configuration flags
-    GCONF = 0x00,
+    GCONF = 0x00, ReadWrite, GConf;
     /// Global status flags
-    GSTAT = 0x01,
+    GSTAT = 0x01, ReadClear, GStat;
     /// UART transmission counter
-    IFCNT = 0x02,
+    IFCNT = 0x02, Read, value 8;
     /// UART slave configuration
-    SLAVECONF = 0x03,
+    SLAVECONF = 0x03, Write, NodeConf;
     /// Read input / write output pins
-    IOIN = 0x04,
+    IOIN = 0x04, ReadWrite, IoIn;
     /// Position comparison register
-    X_COMPARE = 0x05,
+    X_COMPARE = 0x05, Write, signed 32;
     /// OTP programming register
-    OTP_PROG = 0x06,
+    OTP_PROG = 0x06, Write, OtpProg;
     /// OTP read register
-    OTP_READ = 0x07,
+    OTP_READ = 0x07, Read, OtpRead;
     /// Factory configuration (clock trim)
-    FACTORY_CONF = 0x08,
+    FACTORY_CONF = 0x08, ReadWrite, FactoryConf;
     /// Short detector configuration
-    SHORT_CONF = 0x09,
+    SHORT_CONF = 0x09, Write, ShortConf;
     /// Driver configuration
-    DRV_CONF = 0x0A,
+    DRV_CONF = 0x0A, Write, DrvConf;
     /// Global scaling of motor current
-    GLOBALSCALER = 0x0B,
+    GLOBALSCALER = 0x0B, Write, value 8;
     /// Offset calibration results
-    OFFSET_READ = 0x0C,
+    OFFSET_READ = 0x0C, Read, OffsetRead;
 
     /* Velocity dependent driver feature control registers */
     /// Driver current control
-    IHOLD_IRUN = 0x10,
+    IHOLD_IRUN = 0x10, Write, IHoldIRun;
     /// Delay before power down
-    TPOWERDOWN = 0x11,
+    TPOWERDOWN = 0x11, Write, value 8;
     /// Actual time between microsteps
-    TSTEP = 0x12,
+    TSTEP = 0x12, Read, value 20;
     /// Upper velocity for stealthChop voltage PWM mode
-    TPWMTHRS = 0x13,
+    TPWMTHRS = 0x13, Write, value 20;
     /// Lower threshold velocity for switching on smart energy coolStep and stallGuard feature
-    TCOOLTHRS = 0x14,
+    TCOOLTHRS = 0x14, Write, value 20;
     /// Velocity threshold for switching into a different chopper mode and fullstepping
-    THIGH = 0x15,
+    THIGH = 0x15, Write, value 20;
 
     /* Ramp generator motion control registers */
     /// Driving mode (Velocity, Positioning, Hold)
-    RAMPMODE = 0x20,
+    RAMPMODE = 0x20, ReadWrite, value 2;
     /// Actual motor position
-    XACTUAL = 0x21,
+    XACTUAL = 0x21, ReadWrite, signed 32;
     /// Actual  motor  velocity  from  ramp  generator
-    VACTUAL = 0x22,
+    VACTUAL = 0x22, Read, signed 24;
     /// Motor start velocity
-    VSTART = 0x23,
+    VSTART = 0x23, Write, value 18;
     /// First acceleration between VSTART and V1
-    A1 = 0x24,
+    A1 = 0x24, Write, value 16;
     /// First acceleration/deceleration phase target velocity
-    V1 = 0x25,
+    V1 = 0x25, Write, value 20;
     /// Second acceleration between V1 and VMAX
-    AMAX = 0x26,
+    AMAX = 0x26, Write, value 16;
     /// Target velocity in velocity mode
-    VMAX = 0x27,
+    VMAX = 0x27, Write, value 23;
     /// Deceleration between VMAX and V1
-    DMAX = 0x28,
+    DMAX = 0x28, Write, value 16;
     /// Deceleration between V1 and VSTOP
     /// Attention:  Do  not  set  0  in  positioning  mode, even if V1=0!
-    D1 = 0x2A,
+    D1 = 0x2A, Write, value 16;
     /// Motor stop velocity
     /// Attention: Set VSTOP > VSTART!
     /// Attention:  Do  not  set  0  in  positioning  mode, minimum 10 recommend!
-    VSTOP = 0x2B,
+    VSTOP = 0x2B, Write, value 18;
     /// Waiting time after ramping down to zero velocity before next movement or direction inversion can start.
-    TZEROWAIT = 0x2C,
+    TZEROWAIT = 0x2C, Write, value 16;
     /// Target position for ramp mode
-    XTARGET = 0x2D,
+    XTARGET = 0x2D, ReadWrite, signed 32;
 
     /* Ramp generator driver feature control registers */
     /// Velocity threshold for enabling automatic commutation dcStep
-    VDCMIN = 0x33,
+    VDCMIN = 0x33, Write, value 23;
     /// Switch mode configuration
-    SW_MODE = 0x34,
+    SW_MODE = 0x34, ReadWrite, SwMode;
     /// Ramp status and switch event status
-    RAMP_STAT = 0x35,
+    RAMP_STAT = 0x35, ReadClear, RampStat;
     /// Ramp generator latch position upon programmable switch event
-    XLATCH = 0x36,
+    XLATCH = 0x36, Read, signed 32;
 
     /* Encoder registers */
     /// Encoder configuration and use of N channel
-    ENCMODE = 0x38,
+    ENCMODE = 0x38, ReadWrite, EncMode;
     /// Actual encoder position
-    X_ENC = 0x39,
+    X_ENC = 0x39, ReadWrite, signed 32;
     /// Accumulation constant
-    ENC_CONST = 0x3A,
+    ENC_CONST = 0x3A, Write, value 32;
     /// Encoder status information
-    ENC_STATUS = 0x3B,
+    ENC_STATUS = 0x3B, ReadClear, EncStatus;
     /// Encoder position latched on N event
-    ENC_LATCH = 0x3C,
+    ENC_LATCH = 0x3C, Read, signed 32;
     /// Maximum number of steps deviation between encoder counter and XACTUAL for deviation warning
-    ENC_DEVIATION = 0x3D,
+    ENC_DEVIATION = 0x3D, Write, value 20;
 
     /* Motor driver registers */
     /// Microstep table entries. Add 0...7 for the next registers
-    MSLUT_0_7 = 0x60,
+    MSLUT_0_7 = 0x60, Write, value 32;
+    /// Microstep table entries 32 to 63
+    MSLUT_1 = 0x61, Write, value 32;
+    /// Microstep table entries 64 to 95
+    MSLUT_2 = 0x62, Write, value 32;
+    /// Microstep table entries 96 to 127
+    MSLUT_3 = 0x63, Write, value 32;
+    /// Microstep table entries 128 to 159
+    MSLUT_4 = 0x64, Write, value 32;
+    /// Microstep table entries 160 to 191
+    MSLUT_5 = 0x65, Write, value 32;
+    /// Microstep table entries 192 to 223
+    MSLUT_6 = 0x66, Write, value 32;
+    /// Microstep table entries 224 to 255
+    MSLUT_7 = 0x67, Write, value 32;
     /// Look up table segmentation definition
-    MSLUTSEL = 0x68,
+    MSLUTSEL = 0x68, Write, MsLutSel;
     /// Absolute current at microstep table entries 0 and 256
-    MSLUTSTART = 0x69,
+    MSLUTSTART = 0x69, Write, MsLutStart;
     /// Actual position in the microstep table
-    MSCNT = 0x6A,
+    MSCNT = 0x6A, Read, value 10;
     /// Actual microstep current
-    MSCURACT = 0x6B,
+    MSCURACT = 0x6B, Read, MsCurAct;
     /// Chopper and driver configuration
-    CHOPCONF = 0x6C,
+    CHOPCONF = 0x6C, ReadWrite, ChopConf;
     /// coolStep smart current control register and stallGuard2 configuration
-    COOLCONF = 0x6D,
+    COOLCONF = 0x6D, Write, CoolConf;
     /// dcStep automatic commutation configuration register
-    DCCTRL = 0x6E,
+    DCCTRL = 0x6E, Write, DcCtrl;
     /// stallGuard2 to_val and driver error flags
-    DRV_STATUS = 0x6F,
+    DRV_STATUS = 0x6F, Read, DrvStatus;
     /// stealthChop voltage PWM mode chopper configuration
-    PWMCONF = 0x70,
+    PWMCONF = 0x70, Write, PwmConf;
     /// Results of stealthChop amplitude regulator.
-    PWM_SCALE = 0x71,
+    PWM_SCALE = 0x71, Read, PwmScale;
     /// Automatically determined PWM config to_vals
-    PWM_AUTO = 0x72,
+    PWM_AUTO = 0x72, Read, PwmAuto;
     /// Number of input steps skipped due to dcStep. only with SD_MODE = 1
-    LOST_STEPS = 0x73,
+    LOST_STEPS = 0x73, Read, value 20;
+}
+
+impl Registers {
+    /// microstep table registers MSLUT[0] to MSLUT[7]
+    pub const MSLUT: [Registers; 8] = [
+        Registers::MSLUT_0_7,
+        Registers::MSLUT_1,
+        Registers::MSLUT_2,
+        Registers::MSLUT_3,
+        Registers::MSLUT_4,
+        Registers::MSLUT_5,
+        Registers::MSLUT_6,
+        Registers::MSLUT_7,
+    ];
+
+    /// all registers that can be read, in address order
+    pub const READABLE: [Registers; READABLE_COUNT] = readable();
+}
+
+const READABLE_COUNT: usize = {
+    let mut count = 0;
+    let mut i = 0;
+    while i < REGISTER_COUNT {
+        if Registers::ALL[i].access().is_readable() {
+            count += 1;
+        }
+        i += 1;
+    }
+    count
+};
+
+/// collect the readable registers of the table
+const fn readable() -> [Registers; READABLE_COUNT] {
+    let mut readable = [Registers::GCONF; READABLE_COUNT];
+    let mut count = 0;
+    let mut i = 0;
+    while i < REGISTER_COUNT {
+        if Registers::ALL[i].access().is_readable() {
+            readable[count] = Registers::ALL[i];
+            count += 1;
+        }
+        i += 1;
+    }
+    readable
+}
+
+impl Registers {
+    /// register at `addr`, `None` for unused addresses
+    pub fn from_addr(addr: u8) -> Option<Registers> {
+        Registers::ALL.iter().copied().find(|register| *register as u8 == addr)
+    }
 }
 
 impl Address for Registers {
@@ -153,7 +417,7 @@ impl Address for Registers {
 #[allow(dead_code)]
 #[derive(Clone, Copy)]
 #[bitfield(bits = 8)]
-#[derive(ToValue)]
+#[derive(ToValue, Debug, PartialEq, Eq, Default)]
 pub struct SpiStatus {
     pub reset_flag: bool,
     pub driver_error: bool,
@@ -165,194 +429,389 @@ pub struct SpiStatus {
     pub status_stop_r: bool,
 }
 
-/// DRVSTATUS
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct DrvStatus {
-    pub sg_result: B10,
-    #[skip] _a: B2,
-    pub s2vsa: bool,
-    pub s2vsb: bool,
-    pub stealth: bool,
-    pub fsactive: bool,
-    pub cs_actual: B5,
-    #[skip] _b: B3,
-    pub stallguard: bool,
-    pub ot: bool,
-    pub otpw: bool,
-    pub s2ga: bool,
-    pub s2gb: bool,
-    pub ola: bool,
-    pub olb: bool,
-    pub standstill: bool,
+/// Flag of the SPI status, the value is its bit mask
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SpiFlag {
+    /// the chip has been reset since the last read of GSTAT
+    ResetFlag = 0x01,
+    /// the driver has been shut down (overtemperature or short circuit)
+    DriverError = 0x02,
+    /// stallGuard threshold reached
+    Sg2 = 0x04,
+    /// the motor is at standstill
+    Standstill = 0x08,
+    /// VACTUAL equals VMAX
+    VelocityReached = 0x10,
+    /// XACTUAL equals XTARGET
+    PositionReached = 0x20,
+    /// left reference switch active
+    StatusStopL = 0x40,
+    /// right reference switch active
+    StatusStopR = 0x80,
 }
 
-/// GCONF Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct GConf {
-    pub recalibrate: bool,
-    pub faststandstill: bool,
-    pub en_pwm_mode: bool,
-    pub multistep_filt: bool,
-    pub shaft: bool,
-    pub diag0_error: bool,
-    pub diag0_otp: bool,
-    pub diag0_stall: bool,
-    pub diag1_stall: bool,
-    pub diag1_index: bool,
-    pub diag1_onstate: bool,
-    pub diag1_steps_skipped: bool,
-    pub diag0_int_pushpull: bool,
-    pub diag1_poscomp_pushpull: bool,
-    pub small_hysteresis: bool,
-    pub stop_enable: bool,
-    pub direct_mode: bool,
-    #[skip] test_mode: bool,
-    #[skip] _fill: B14,
-}
-
-/// GSTAT Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct GStat {
-    pub reset: bool,
-    pub drv_err: bool,
-    pub uv_cp: bool,
-    #[skip] _fill: B29,
+impl SpiFlag {
+    const ALL: [SpiFlag; 8] = [
+        SpiFlag::ResetFlag,
+        SpiFlag::DriverError,
+        SpiFlag::Sg2,
+        SpiFlag::Standstill,
+        SpiFlag::VelocityReached,
+        SpiFlag::PositionReached,
+        SpiFlag::StatusStopL,
+        SpiFlag::StatusStopR,
+    ];
+}
+
+/// Set operations on the status flags
+///
+/// ```
+/// use tmc5160::registers::{SpiFlag, SpiStatus};
+/// let status = SpiStatus::from_bytes([0x0A]);
+/// assert!(status.contains(SpiFlag::Standstill));
+/// assert!(status.any(SpiStatus::ERROR_FLAGS));
+/// assert!(!status.all(SpiStatus::ERROR_FLAGS));
+/// assert!(status.flags().eq([SpiFlag::DriverError, SpiFlag::Standstill]));
+/// ```
+impl SpiStatus {
+    /// flags reporting a reset or a driver shutdown
+    pub const ERROR_FLAGS: u8 = SpiFlag::ResetFlag as u8 | SpiFlag::DriverError as u8;
+    /// flags of the reference switches
+    pub const STOP_FLAGS: u8 = SpiFlag::StatusStopL as u8 | SpiFlag::StatusStopR as u8;
+    /// flags reporting the state of the ramp generator
+    pub const MOTION_FLAGS: u8 = SpiFlag::Standstill as u8
+        | SpiFlag::VelocityReached as u8
+        | SpiFlag::PositionReached as u8;
+
+    /// status byte
+    pub fn bits(self) -> u8 {
+        self.into_bytes()[0]
+    }
+
+    /// check if `flag` is set
+    pub fn contains(self, flag: SpiFlag) -> bool {
+        self.bits() & flag as u8 != 0
+    }
+
+    /// check if any flag of `mask` is set
+    pub fn any(self, mask: u8) -> bool {
+        self.bits() & mask != 0
+    }
+
+    /// check if all flags of `mask` are set
+    pub fn all(self, mask: u8) -> bool {
+        self.bits() & mask == mask
+    }
+
+    /// check if no flag is set
+    pub fn is_empty(self) -> bool {
+        self.bits() == 0
+    }
+
+    /// iterate over the set flags, from bit 0 to bit 7
+    pub fn flags(self) -> SpiFlags {
+        SpiFlags { bits: self.bits() }
+    }
+}
+
+/// Iterator over the set flags of a `SpiStatus`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpiFlags {
+    bits: u8,
+}
+
+impl Iterator for SpiFlags {
+    type Item = SpiFlag;
+
+    fn next(&mut self) -> Option<SpiFlag> {
+        if self.bits == 0 {
+            return None;
+        }
+        let i = self.bits.trailing_zeros();
+        self.bits &= !(1 << i);
+        Some(SpiFlag::ALL[i as usize])
+    }
+}
+
+register! {
+    /// DRVSTATUS
+    #[derive(Default)]
+    DrvStatus @ DRV_STATUS {
+        pub sg_result: B10,
+        #[skip] _a: B2,
+        pub s2vsa: bool,
+        pub s2vsb: bool,
+        pub stealth: bool,
+        pub fsactive: bool,
+        pub cs_actual: B5,
+        #[skip] _b: B3,
+        pub stallguard: bool,
+        pub ot: bool,
+        pub otpw: bool,
+        pub s2ga: bool,
+        pub s2gb: bool,
+        pub ola: bool,
+        pub olb: bool,
+        pub standstill: bool,
+    }
+}
+
+register! {
+    /// GCONF Register
+    #[derive(Default)]
+    GConf @ GCONF {
+        pub recalibrate: bool,
+        pub faststandstill: bool,
+        pub en_pwm_mode: bool,
+        pub multistep_filt: bool,
+        pub shaft: bool,
+        pub diag0_error: bool,
+        pub diag0_otp: bool,
+        pub diag0_stall: bool,
+        pub diag1_stall: bool,
+        pub diag1_index: bool,
+        pub diag1_onstate: bool,
+        pub diag1_steps_skipped: bool,
+        pub diag0_int_pushpull: bool,
+        pub diag1_poscomp_pushpull: bool,
+        pub small_hysteresis: bool,
+        pub stop_enable: bool,
+        pub direct_mode: bool,
+        #[skip] test_mode: bool,
+        #[skip] _fill: B14,
+    }
+}
+
+register! {
+    /// GSTAT Register
+    #[derive(Default)]
+    GStat @ GSTAT {
+        pub reset: bool,
+        pub drv_err: bool,
+        pub uv_cp: bool,
+        #[skip] _fill: B29,
+    }
 }
 
 // IFCNT Register is disabled in SPI mode
 
-/// NODECONF Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct NodeConf {
-    pub nodeaddr: u8,
-    pub senddelay: B4,
-    #[skip] _fill: B20,
+register! {
+    /// NODECONF Register
+    #[derive(Default)]
+    NodeConf @ SLAVECONF {
+        pub nodeaddr: u8,
+        pub senddelay: B4,
+        #[skip] _fill: B20,
+    }
 }
 
+/// SENDDELAY of SLAVECONF, delay before the reply to a UART read request in bit times
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SendDelay {
+    /// 8 bit times, only for a single node on the bus
+    Bits8 = 0x00,
+    /// 3 * 8 bit times
+    Bits24 = 0x02,
+    /// 5 * 8 bit times
+    Bits40 = 0x04,
+    /// 7 * 8 bit times
+    Bits56 = 0x06,
+    /// 9 * 8 bit times
+    Bits72 = 0x08,
+    /// 11 * 8 bit times
+    Bits88 = 0x0A,
+    /// 13 * 8 bit times
+    Bits104 = 0x0C,
+    /// 15 * 8 bit times
+    Bits120 = 0x0E,
+}
 
-/// IOIN Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct IoIn {
-    pub refl_step: bool,
-    pub refr_dir: bool,
-    pub encb_dcen_cfg4: bool,
-    pub enca_dcen_cfg5: bool,
-    pub drv_enn: bool,
-    pub enc_n_dco_cfg6: bool,
-    pub sd_mode: bool,
-    pub swcomp_in: bool,
-    pub version: u8,
-    #[skip] _fill: B16,
-}
-
-
-/// OTP_PROG Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct OtpProg {
-    pub otpbit: B3,
-    pub otpbyte: B2,
-    #[skip] __: B3,
-    pub otpmagic: u8,
-    #[skip] _fill: B16,
+impl SendDelay {
+    const ALL: [SendDelay; 8] = [SendDelay::Bits8, SendDelay::Bits24, SendDelay::Bits40, SendDelay::Bits56, SendDelay::Bits72, SendDelay::Bits88, SendDelay::Bits104, SendDelay::Bits120];
+
+    /// convert the senddelay field value to the enum (odd values act like the next lower even value)
+    pub fn from_bits(bits: u8) -> Self {
+        Self::ALL[((bits & 0x0F) >> 1) as usize]
+    }
+
+    /// delay in bit times
+    pub fn bit_times(self) -> u32 {
+        (self as u32 + 1) * 8
+    }
+
+    /// check if the delay is long enough for a bus with several nodes (at least 24 bit times)
+    pub fn is_multi_drop_safe(self) -> bool {
+        self != SendDelay::Bits8
+    }
 }
 
-/// OTPREAD
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct OtpRead {
-    pub otp_fclktrim: B5,
-    pub otp_s2_level: bool,
-    pub otp_bbm: bool,
-    pub otp_tbl: bool,
-    #[skip] _fill: B24,
+register! {
+    /// IOIN Register
+    #[derive(Default)]
+    IoIn @ IOIN {
+        pub refl_step: bool,
+        pub refr_dir: bool,
+        pub encb_dcen_cfg4: bool,
+        pub enca_dcen_cfg5: bool,
+        pub drv_enn: bool,
+        pub enc_n_dco_cfg6: bool,
+        pub sd_mode: bool,
+        pub swcomp_in: bool,
+        #[skip] _fill: B16,
+        /// silicon version (0x30)
+        pub version: u8,
+    }
 }
 
-/// SHORT_CONF
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct ShortConf {
-    pub s2vs_level: B4,
-    #[skip] _a: B4,
-    pub s2g_level: B4,
-    #[skip] _b: B4,
-    pub shortfilter: B2,
-    pub shortdelay: bool,
-    #[skip] _fill: B13,
-}
-
-/// DrvConfRegister
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct DrvConf {
-    /// "Break Before Make" duration specified in ns (0 to 24)
-    pub bbm_time: B4,
-    #[skip] _a: B4,
-    /// "Break Before Make" duration specified in clock cycles (0 to 15).
-    pub bbm_clks: B4,
-    #[skip] _b: B4,
-    /// over temperature selection
-    pub ots_select: B2,
-    /// MOSFET gate driver current (0 to 3)
-    pub drv_strength: B2,
-    /// filter time constant
-    pub filt_isense: B2,
-    #[skip] _fill: B10,
-}
-
-/// OFFSET_READ
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield]
-pub struct OffsetRead {
-    pub phase_a: u8,
-    pub phase_b: u8,
+
+register! {
+    /// OTP_PROG Register
+    #[derive(Default)]
+    OtpProg @ OTP_PROG {
+        pub otpbit: B3,
+        #[skip] _a: B1,
+        pub otpbyte: B2,
+        #[skip] _b: B2,
+        pub otpmagic: u8,
+        #[skip] _fill: B16,
+    }
 }
 
+register! {
+    /// OTPREAD
+    #[derive(Default)]
+    OtpRead @ OTP_READ {
+        /// reset default for FCLKTRIM (factory trimmed to 12 MHz)
+        pub otp_fclktrim: B5,
+        /// reset default for the short detection levels
+        pub otp_s2_level: bool,
+        /// reset default for the break before make time
+        pub otp_bbm: bool,
+        /// reset default for the TBL comparator blank time
+        pub otp_tbl: bool,
+        #[skip] _fill: B24,
+    }
+}
 
-/// IHOLD_IRUN Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct IHoldIRun {
-    /// motor hold current
-    pub i_hold: B5,
-    #[skip] _a: B3,
-    /// motor run current
-    pub i_run: B5,
-    #[skip] _b: B3,
-    /// number of clock cycles after motion
-    pub i_hold_delay: B4,
-    #[skip] _c: B4,
-    #[skip] _d: B8,
+impl OtpRead {
+    /// TBL setting after reset (2: 36 clocks or 1: 24 clocks)
+    pub fn default_tbl(&self) -> u8 {
+        if self.otp_tbl() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// BBMCLKS setting after reset (4 or 2 clocks)
+    pub fn default_bbm_clks(&self) -> u8 {
+        if self.otp_bbm() {
+            2
+        } else {
+            4
+        }
+    }
+
+    /// S2VS_LEVEL and S2G_LEVEL setting after reset (6 or 12)
+    pub fn default_s2_level(&self) -> u8 {
+        if self.otp_s2_level() {
+            12
+        } else {
+            6
+        }
+    }
+}
+
+register! {
+    /// FACTORY_CONF Register
+    #[derive(Default)]
+    FactoryConf @ FACTORY_CONF {
+        /// internal clock trim (0: lowest to 31: highest frequency)
+        pub fclktrim: B5,
+        #[skip] _fill: B27,
+    }
+}
+
+register! {
+    /// SHORT_CONF
+    #[derive(Default)]
+    ShortConf @ SHORT_CONF {
+        pub s2vs_level: B4,
+        #[skip] _a: B4,
+        pub s2g_level: B4,
+        #[skip] _b: B4,
+        pub shortfilter: B2,
+        pub shortdelay: bool,
+        #[skip] _fill: B13,
+    }
+}
+
+register! {
+    /// DrvConfRegister
+    #[derive(Default)]
+    DrvConf @ DRV_CONF {
+        /// "Break Before Make" duration specified in ns (0 to 24)
+        pub bbm_time: B5,
+        #[skip] _a: B3,
+        /// "Break Before Make" duration specified in clock cycles (0 to 15).
+        pub bbm_clks: B4,
+        #[skip] _b: B4,
+        /// over temperature selection
+        pub ots_select: B2,
+        /// MOSFET gate driver current (0 to 3)
+        pub drv_strength: B2,
+        /// filter time constant
+        pub filt_isense: B2,
+        #[skip] _fill: B10,
+    }
+}
+
+register! {
+    /// OFFSET_READ
+    #[derive(Default)]
+    OffsetRead @ OFFSET_READ {
+        /// offset calibration result of phase B, 8 bit signed
+        #[signed] pub phase_b: u8,
+        /// offset calibration result of phase A, 8 bit signed
+        #[signed] pub phase_a: u8,
+        #[skip] _fill: u16,
+    }
+}
+
+impl OffsetRead {
+    /// signed offset calibration result of phase A
+    pub fn phase_a_signed(&self) -> i8 {
+        self.phase_a() as i8
+    }
+
+    /// signed offset calibration result of phase B
+    pub fn phase_b_signed(&self) -> i8 {
+        self.phase_b() as i8
+    }
+}
+
+
+register! {
+    /// IHOLD_IRUN Register
+    #[derive(Default)]
+    IHoldIRun @ IHOLD_IRUN {
+        /// motor hold current
+        pub i_hold: B5,
+        #[skip] _a: B3,
+        /// motor run current
+        pub i_run: B5,
+        #[skip] _b: B3,
+        /// number of clock cycles after motion
+        pub i_hold_delay: B4,
+        #[skip] _c: B4,
+        #[skip] _d: B8,
+    }
 }
 
 /// RAMPMODE Register
 #[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum RampMode {
     /// using all A, D and V parameters
     PositioningMode = 0x00,
@@ -364,123 +823,341 @@ pub enum RampMode {
     HoldMode = 0x03,
 }
 
+impl RampMode {
+    const ALL: [RampMode; 4] = [RampMode::PositioningMode, RampMode::VelocityModePos, RampMode::VelocityModeNeg, RampMode::HoldMode];
 
-/// SW_MODE Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct SwMode {
-    pub stop_l_enable: bool,
-    pub stop_r_enable: bool,
-    pub pol_stop_l: bool,
-    pub pol_stop_r: bool,
-    pub swap_lr: bool,
-    pub latch_l_active: bool,
-    pub latch_l_inactive: bool,
-    pub latch_r_active: bool,
-    pub latch_r_inactive: bool,
-    pub en_latch_encoder: bool,
-    pub sg_stop: bool,
-    pub en_softstop: bool,
-    #[skip] _fill: B20,
-}
-
-
-/// RAMOSTAT Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct RampStat {
-    pub status_stop_l: bool,
-    pub status_stop_r: bool,
-    pub status_latch_l: bool,
-    pub status_latch_r: bool,
-    pub event_stop_l: bool,
-    pub event_stop_r: bool,
-    pub event_stop_sg: bool,
-    pub event_pos_reached: bool,
-    pub velocity_reached: bool,
-    pub position_reached: bool,
-    pub vzero: bool,
-    pub t_zerowait_active: bool,
-    pub second_move: bool,
-    pub status_sg: bool,
-    #[skip] _fill: B18,
+    /// convert the RAMPMODE register value to the enum
+    pub fn from_bits(bits: u8) -> Self {
+        Self::ALL[(bits & 0b11) as usize]
+    }
 }
 
 
-/// ENCMODE Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct EncMode {
-    pub pol_a: bool,
-    pub pol_b: bool,
-    pub pol_n: bool,
-    pub ignore_ab: bool,
-    pub clr_cont: bool,
-    pub clr_once: bool,
-    pub pos_edge: bool,
-    pub neg_edge: bool,
-    pub clr_enc_x: bool,
-    pub latch_x_act: bool,
-    pub enc_sel_decimal: bool,
-    #[skip] _fill: B21,
-}
-
-/// ENC_STATUS Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct EncStatus {
-    pub n_event: bool,
-    pub deviation_warn: bool,
-    #[skip] _fill: B30,
+register! {
+    /// SW_MODE Register
+    #[derive(Default)]
+    SwMode @ SW_MODE {
+        pub stop_l_enable: bool,
+        pub stop_r_enable: bool,
+        pub pol_stop_l: bool,
+        pub pol_stop_r: bool,
+        pub swap_lr: bool,
+        pub latch_l_active: bool,
+        pub latch_l_inactive: bool,
+        pub latch_r_active: bool,
+        pub latch_r_inactive: bool,
+        pub en_latch_encoder: bool,
+        pub sg_stop: bool,
+        pub en_softstop: bool,
+        #[skip] _fill: B20,
+    }
 }
 
 
-/// MSLUTSEL Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct MsLutSel {
-    pub w0: B2,
-    pub w1: B2,
-    pub w2: B2,
-    pub w3: B2,
-    pub x1: u8,
-    pub x2: u8,
-    pub x3: u8,
-}
-
-/// CHOPFCONF Register
+register! {
+    /// RAMOSTAT Register
+    #[derive(Default)]
+    RampStat @ RAMP_STAT {
+        pub status_stop_l: bool,
+        pub status_stop_r: bool,
+        pub status_latch_l: bool,
+        pub status_latch_r: bool,
+        pub event_stop_l: bool,
+        pub event_stop_r: bool,
+        pub event_stop_sg: bool,
+        pub event_pos_reached: bool,
+        pub velocity_reached: bool,
+        pub position_reached: bool,
+        pub vzero: bool,
+        pub t_zerowait_active: bool,
+        pub second_move: bool,
+        pub status_sg: bool,
+        #[skip] _fill: B18,
+    }
+}
+
+
+/// Write-to-clear flags of the RAMP_STAT register
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RampEvent {
+    /// latch left ready (status_latch_l)
+    LatchL,
+    /// latch right ready (status_latch_r)
+    LatchR,
+    /// stop by stallGuard (event_stop_sg)
+    StopSg,
+    /// target position reached (event_pos_reached)
+    PosReached,
+    /// second ramp move in the opposite direction (second_move)
+    SecondMove,
+}
+
+impl RampEvent {
+    /// all write-to-clear flags
+    pub const ALL: [RampEvent; 5] = [
+        RampEvent::LatchL,
+        RampEvent::LatchR,
+        RampEvent::StopSg,
+        RampEvent::PosReached,
+        RampEvent::SecondMove,
+    ];
+
+    /// set the flag of this event in `ramp_stat`
+    pub fn set_in(self, ramp_stat: &mut RampStat) {
+        match self {
+            RampEvent::LatchL => ramp_stat.set_status_latch_l(true),
+            RampEvent::LatchR => ramp_stat.set_status_latch_r(true),
+            RampEvent::StopSg => ramp_stat.set_event_stop_sg(true),
+            RampEvent::PosReached => ramp_stat.set_event_pos_reached(true),
+            RampEvent::SecondMove => ramp_stat.set_second_move(true),
+        }
+    }
+}
+
+register! {
+    /// ENCMODE Register
+    #[derive(Default)]
+    EncMode @ ENCMODE {
+        pub pol_a: bool,
+        pub pol_b: bool,
+        pub pol_n: bool,
+        pub ignore_ab: bool,
+        pub clr_cont: bool,
+        pub clr_once: bool,
+        pub pos_edge: bool,
+        pub neg_edge: bool,
+        pub clr_enc_x: bool,
+        pub latch_x_act: bool,
+        pub enc_sel_decimal: bool,
+        #[skip] _fill: B21,
+    }
+}
+
+/// N channel event sensitivity of ENCMODE (pos_edge, neg_edge)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NEventSensitivity {
+    /// while N is active
+    Level,
+    /// when N becomes active
+    RisingEdge,
+    /// when N becomes inactive
+    FallingEdge,
+    /// on both edges of N
+    BothEdges,
+}
+
+/// Latching and clearing of X_ENC on an N event (clr_once, clr_cont)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EncoderClear {
+    /// no latch on N events
+    Disabled,
+    /// on the next N event after writing ENCMODE
+    Once,
+    /// on every N event, e.g. once per revolution
+    Always,
+}
+
+impl EncMode {
+    /// select the active level of N and the sensitivity of the N event
+    pub fn with_n_channel(self, active_high: bool, sensitivity: NEventSensitivity) -> Self {
+        let (pos_edge, neg_edge) = match sensitivity {
+            NEventSensitivity::Level => (false, false),
+            NEventSensitivity::RisingEdge => (true, false),
+            NEventSensitivity::FallingEdge => (false, true),
+            NEventSensitivity::BothEdges => (true, true),
+        };
+        self.with_pol_n(active_high)
+            .with_pos_edge(pos_edge)
+            .with_neg_edge(neg_edge)
+    }
+
+    /// sensitivity of the N event
+    pub fn n_event_sensitivity(&self) -> NEventSensitivity {
+        match (self.pos_edge(), self.neg_edge()) {
+            (false, false) => NEventSensitivity::Level,
+            (true, false) => NEventSensitivity::RisingEdge,
+            (false, true) => NEventSensitivity::FallingEdge,
+            (true, true) => NEventSensitivity::BothEdges,
+        }
+    }
+
+    /// require the levels of A and B for an N event (`Some((a, b))`) or ignore them (`None`)
+    pub fn with_ab_condition(self, condition: Option<(bool, bool)>) -> Self {
+        let (pol_a, pol_b) = condition.unwrap_or((false, false));
+        self.with_ignore_ab(condition.is_none())
+            .with_pol_a(pol_a)
+            .with_pol_b(pol_b)
+    }
+
+    /// select when an N event latches X_ENC (and XACTUAL with latch_x_act), `clear_x_enc` also
+    /// clears X_ENC
+    pub fn with_clear(self, clear: EncoderClear, clear_x_enc: bool) -> Self {
+        self.with_clr_once(clear == EncoderClear::Once)
+            .with_clr_cont(clear == EncoderClear::Always)
+            .with_clr_enc_x(clear_x_enc)
+    }
+
+    /// latching of X_ENC on N events, `None` if both clr_once and clr_cont are set
+    pub fn clear(&self) -> Option<EncoderClear> {
+        match (self.clr_once(), self.clr_cont()) {
+            (false, false) => Some(EncoderClear::Disabled),
+            (true, false) => Some(EncoderClear::Once),
+            (false, true) => Some(EncoderClear::Always),
+            (true, true) => None,
+        }
+    }
+
+    /// interpret ENC_CONST as decimal (`true`, fraction in 1/10000) or binary (fraction in 1/65536)
+    pub fn with_decimal_const(self, decimal: bool) -> Self {
+        self.with_enc_sel_decimal(decimal)
+    }
+
+    /// check that no mutually exclusive options are combined
+    ///
+    /// clr_once and clr_cont exclude each other, pol_a and pol_b have no effect with ignore_ab.
+    pub fn is_valid(&self) -> bool {
+        self.clear().is_some() && !(self.ignore_ab() && (self.pol_a() || self.pol_b()))
+    }
+}
+
+register! {
+    /// ENC_STATUS Register
+    #[derive(Default)]
+    EncStatus @ ENC_STATUS {
+        pub n_event: bool,
+        pub deviation_warn: bool,
+        #[skip] _fill: B30,
+    }
+}
+
+
+register! {
+    /// MSLUTSEL Register
+    #[derive(Default)]
+    MsLutSel @ MSLUTSEL {
+        pub w0: B2,
+        pub w1: B2,
+        pub w2: B2,
+        pub w3: B2,
+        pub x1: u8,
+        pub x2: u8,
+        pub x3: u8,
+    }
+}
+
+register! {
+    /// MSLUTSTART Register
+    #[derive(Default)]
+    MsLutStart @ MSLUTSTART {
+        /// absolute current at microstep table entry 0
+        pub start_sin: u8,
+        #[skip] _a: B8,
+        /// absolute current for microstep table entry at positions 256
+        pub start_sin90: u8,
+        #[skip] _b: B8,
+    }
+}
+
+register! {
+    /// DCCTRL Register
+    #[derive(Default)]
+    DcCtrl @ DCCTRL {
+        /// upper PWM on time limit for commutation in clock cycles
+        pub dc_time: B10,
+        #[skip] _a: B6,
+        /// max. PWM on time for step loss detection using dcStep in multiples of 16 clock cycles
+        pub dc_sg: u8,
+        #[skip] _b: B8,
+    }
+}
+
+register! {
+    /// MSCURACT Register
+    #[derive(Default)]
+    MsCurAct @ MSCURACT {
+        /// actual microstep current for motor phase A (sine wave), 9 bit signed
+        #[signed] pub cur_a: B9,
+        #[skip] _a: B7,
+        /// actual microstep current for motor phase B (cosine wave), 9 bit signed
+        #[signed] pub cur_b: B9,
+        #[skip] _b: B7,
+    }
+}
+
+impl MsCurAct {
+    /// signed microstep current of phase A (-255 to 255)
+    pub fn cur_a_signed(&self) -> i16 {
+        sign_extend_9(self.cur_a())
+    }
+
+    /// signed microstep current of phase B (-255 to 255)
+    pub fn cur_b_signed(&self) -> i16 {
+        sign_extend_9(self.cur_b())
+    }
+}
+
+/// XDIRECT Register (XTARGET address, only used with GCONF.direct_mode)
 #[derive(Clone, Copy)]
 #[allow(dead_code)]
 #[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct ChopConf {
-    pub toff: B4,
-    pub hstr: B3,
-    pub hend: B4,
-    pub fd3: bool,
-    pub disfdcc: bool,
-    #[skip] _a: B1,
-    pub chm: bool,
-    pub tbl: B2,
-    #[skip] _b: B1,
-    pub vhighfs: bool,
-    pub vhighchm: bool,
-    pub tpfd: B4,
-    pub mres: B4,
-    pub intpol: bool,
-    pub dedge: bool,
-    pub diss2g: bool,
-    pub diss2vs: bool,
+#[derive(ToValue, Debug, PartialEq, Eq, Default)]
+pub struct XDirect {
+    /// coil A current, 9 bit signed
+    pub coil_a: B9,
+    #[skip] _a: B7,
+    /// coil B current, 9 bit signed
+    pub coil_b: B9,
+    #[skip] _b: B7,
+}
+
+impl XDirect {
+    /// create from signed coil currents (-255 to 255), `None` if out of range
+    pub fn from_signed(coil_a: i16, coil_b: i16) -> Option<Self> {
+        if !(-255..=255).contains(&coil_a) || !(-255..=255).contains(&coil_b) {
+            return None;
+        }
+        Some(XDirect::new()
+            .with_coil_a(coil_a as u16 & 0x1FF)
+            .with_coil_b(coil_b as u16 & 0x1FF))
+    }
+
+    /// signed coil A current (-255 to 255)
+    pub fn coil_a_signed(&self) -> i16 {
+        sign_extend_9(self.coil_a())
+    }
+
+    /// signed coil B current (-255 to 255)
+    pub fn coil_b_signed(&self) -> i16 {
+        sign_extend_9(self.coil_b())
+    }
+}
+
+fn sign_extend_9(val: u16) -> i16 {
+    ((val << 7) as i16) >> 7
+}
+
+register! {
+    /// CHOPFCONF Register
+    ChopConf @ CHOPCONF {
+        pub toff: B4,
+        pub hstr: B3,
+        pub hend: B4,
+        pub fd3: bool,
+        pub disfdcc: bool,
+        #[skip] _a: B1,
+        pub chm: bool,
+        pub tbl: B2,
+        /// high sensitivity, low sense resistor voltage (TMC5130 only, reserved on TMC5160/TMC5161)
+        pub vsense: bool,
+        pub vhighfs: bool,
+        pub vhighchm: bool,
+        pub tpfd: B4,
+        pub mres: B4,
+        pub intpol: bool,
+        pub dedge: bool,
+        pub diss2g: bool,
+        pub diss2vs: bool,
+    }
 }
 
 impl Default for ChopConf {
@@ -490,41 +1167,128 @@ impl Default for ChopConf {
 }
 
 
-/// COOLCONF Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct CoolConf {
-    pub semin: B4,
-    #[skip] _a: B1,
-    pub seup: B2,
-    #[skip] _b: B1,
-    pub semax: B4,
-    #[skip] _c: B1,
-    pub sedn: B2,
-    pub seimin: bool,
-    pub sgt: B7,
-    #[skip] _d: B1,
-    pub sfilt: bool,
-    #[skip] _e: B7,
-}
-
-/// PWMCONF Register
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-#[bitfield(bits = 32)]
-#[derive(ToValue)]
-pub struct PwmConf {
-    pub pwm_ofs: u8,
-    pub pwm_grad: u8,
-    pub pwm_freq: B2,
-    pub pwm_autoscale: bool,
-    pub pwm_autograd: bool,
-    pub free_wheel: B2,
-    #[skip] __: B2,
-    pub pwm_reg: B4,
-    pub pwm_lim: B4,
+register! {
+    /// COOLCONF Register
+    #[derive(Default)]
+    CoolConf @ COOLCONF {
+        pub semin: B4,
+        #[skip] _a: B1,
+        pub seup: B2,
+        #[skip] _b: B1,
+        pub semax: B4,
+        #[skip] _c: B1,
+        pub sedn: B2,
+        pub seimin: bool,
+        #[signed] pub sgt: B7,
+        #[skip] _d: B1,
+        pub sfilt: bool,
+        #[skip] _e: B7,
+    }
+}
+
+register! {
+    /// PWMCONF Register
+    PwmConf @ PWMCONF {
+        pub pwm_ofs: u8,
+        pub pwm_grad: u8,
+        pub pwm_freq: B2,
+        pub pwm_autoscale: bool,
+        pub pwm_autograd: bool,
+        pub free_wheel: B2,
+        #[skip] __: B2,
+        pub pwm_reg: B4,
+        pub pwm_lim: B4,
+    }
+}
+
+register! {
+    /// PWM_SCALE Register
+    #[derive(Default)]
+    PwmScale @ PWM_SCALE {
+        /// actual PWM duty cycle (0 to 255)
+        pub pwm_scale_sum: u8,
+        #[skip] _a: u8,
+        /// result of the automatic amplitude regulation based on current measurement, 9 bit signed
+        #[signed] pub pwm_scale_auto: B9,
+        #[skip] _b: B7,
+    }
+}
+
+impl PwmScale {
+    /// signed result of the automatic amplitude regulation (-255 to 255), not on the TMC5130
+    pub fn pwm_scale_auto_signed(&self) -> i16 {
+        sign_extend_9(self.pwm_scale_auto())
+    }
+}
+
+register! {
+    /// PWM_AUTO Register
+    #[derive(Default)]
+    PwmAuto @ PWM_AUTO {
+        /// automatically determined offset value
+        pub pwm_ofs_auto: u8,
+        #[skip] _a: u8,
+        /// automatically determined gradient value
+        pub pwm_grad_auto: u8,
+        #[skip] _b: u8,
+    }
+}
+
+/// PWM frequency selection of PWMCONF (pwm_freq)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PwmFreq {
+    /// f_PWM = 2/1024 f_CLK
+    Div1024 = 0x00,
+    /// f_PWM = 2/683 f_CLK
+    Div683 = 0x01,
+    /// f_PWM = 2/512 f_CLK
+    Div512 = 0x02,
+    /// f_PWM = 2/410 f_CLK
+    Div410 = 0x03,
+}
+
+impl PwmFreq {
+    const ALL: [PwmFreq; 4] = [PwmFreq::Div1024, PwmFreq::Div683, PwmFreq::Div512, PwmFreq::Div410];
+
+    /// convert the pwm_freq field value to the enum
+    pub fn from_bits(bits: u8) -> Self {
+        Self::ALL[(bits & 0b11) as usize]
+    }
+
+    /// PWM frequency in Hz for the given clock frequency
+    pub fn frequency(self, clock: f32) -> f32 {
+        let divider = match self {
+            PwmFreq::Div1024 => 1024.0,
+            PwmFreq::Div683 => 683.0,
+            PwmFreq::Div512 => 512.0,
+            PwmFreq::Div410 => 410.0,
+        };
+        2.0 * clock / divider
+    }
+
+    /// setting with the PWM frequency closest to `frequency` (Hz) for the given clock frequency
+    pub fn closest(clock: f32, frequency: f32) -> Self {
+        let mut best = PwmFreq::Div1024;
+        for freq in Self::ALL {
+            if (freq.frequency(clock) - frequency).abs() < (best.frequency(clock) - frequency).abs() {
+                best = freq;
+            }
+        }
+        best
+    }
+}
+
+/// Standstill option of PWMCONF (freewheel), active when IHOLD = 0
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FreeWheel {
+    /// normal operation
+    Normal = 0x00,
+    /// freewheeling
+    Freewheel = 0x01,
+    /// coil shorted using low side drivers
+    PassiveBrakeLS = 0x02,
+    /// coil shorted using high side drivers
+    PassiveBrakeHS = 0x03,
 }
 
 impl Default for PwmConf {
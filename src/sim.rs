@@ -0,0 +1,233 @@
+//! Register model of the TMC5160 for testing on the host
+//!
+//! `Tmc5160Sim` implements the SPI traits, so it can be passed to `Tmc5160::new()` in place of a
+//! real bus. It keeps the register file, answers reads with the datasheet's one datagram delay and
+//! runs a simplified ramp generator: XACTUAL moves towards XTARGET (or in the direction of the
+//! velocity mode) at VMAX, without acceleration. Time only advances with `advance_us()`.
+use core::convert::Infallible;
+
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+
+use crate::registers::Registers;
+use crate::INTERNAL_CLOCK;
+
+// write 1 to clear: status_latch_l/r, event_stop_sg, event_pos_reached, second_move
+const RAMP_STAT_EVENTS: u32 = 0x10CC;
+const EVENT_POS_REACHED: u32 = 1 << 7;
+const VELOCITY_REACHED: u32 = 1 << 8;
+const POSITION_REACHED: u32 = 1 << 9;
+const VZERO: u32 = 1 << 10;
+const STANDSTILL: u32 = 1 << 31;
+
+/// Simulated TMC5160 on a SPI bus
+#[derive(Debug, Clone)]
+pub struct Tmc5160Sim {
+    registers: [u32; 128],
+    read_addr: u8,
+    velocity: i32,
+    fraction: f32,
+    clock: f32,
+}
+
+impl Default for Tmc5160Sim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tmc5160Sim {
+    /// create a simulated chip with the reset values of the registers
+    pub fn new() -> Self {
+        let mut registers = [0; 128];
+        registers[Registers::GSTAT as usize] = 0x01;
+        registers[Registers::IOIN as usize] = 0x30 << 24;
+        registers[Registers::TPOWERDOWN as usize] = 10;
+        registers[Registers::CHOPCONF as usize] = 0x1041_0150;
+        registers[Registers::PWMCONF as usize] = 0xC40C_001E;
+        Tmc5160Sim {
+            registers,
+            read_addr: 0,
+            velocity: 0,
+            fraction: 0.0,
+            clock: INTERNAL_CLOCK,
+        }
+    }
+
+    /// set the clock frequency in Hz used for the ramp generator (default 12 MHz)
+    pub fn clock(mut self, clock: f32) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// value of a register as the chip would return it
+    pub fn register(&self, reg: Registers) -> u32 {
+        self.read(reg as u8)
+    }
+
+    /// overwrite a register, e.g. to inject DRV_STATUS error flags
+    pub fn set_register(&mut self, reg: Registers, val: u32) {
+        self.registers[reg as usize] = val;
+    }
+
+    /// actual velocity of the ramp generator (VACTUAL)
+    pub fn velocity(&self) -> i32 {
+        self.velocity
+    }
+
+    /// run the ramp generator for `dt_us` microseconds
+    pub fn advance_us(&mut self, dt_us: u32) {
+        let vmax = (self.registers[Registers::VMAX as usize] & 0x7F_FFFF) as i32;
+        let position = self.registers[Registers::XACTUAL as usize] as i32;
+        let target = self.registers[Registers::XTARGET as usize] as i32;
+        let was_moving = self.velocity != 0;
+
+        self.velocity = match self.registers[Registers::RAMPMODE as usize] & 0x03 {
+            0 if position < target => vmax,
+            0 if position > target => -vmax,
+            0 => 0,
+            1 => vmax,
+            2 => -vmax,
+            _ => self.velocity,
+        };
+
+        // v[usteps/s] = v[usteps/t] * fclk / 2^24
+        self.fraction +=
+            self.velocity as f32 * self.clock / 16_777_216.0 * dt_us as f32 / 1_000_000.0;
+        let steps = self.fraction as i32;
+        self.fraction -= steps as f32;
+        let mut new_position = position.wrapping_add(steps);
+
+        if self.registers[Registers::RAMPMODE as usize] & 0x03 == 0
+            && self.velocity != 0
+            && new_position.wrapping_sub(target).signum() != position.wrapping_sub(target).signum()
+        {
+            new_position = target;
+            self.velocity = 0;
+            self.fraction = 0.0;
+        }
+        self.registers[Registers::XACTUAL as usize] = new_position as u32;
+
+        if was_moving && self.velocity == 0 && new_position == target {
+            self.registers[Registers::RAMP_STAT as usize] |= EVENT_POS_REACHED;
+        }
+    }
+
+    fn ramp_stat(&self) -> u32 {
+        let mut ramp_stat = self.registers[Registers::RAMP_STAT as usize] & RAMP_STAT_EVENTS;
+        ramp_stat |= VELOCITY_REACHED;
+        if self.velocity == 0 {
+            ramp_stat |= VZERO;
+        }
+        if self.registers[Registers::XACTUAL as usize]
+            == self.registers[Registers::XTARGET as usize]
+        {
+            ramp_stat |= POSITION_REACHED;
+        }
+        ramp_stat
+    }
+
+    fn drv_status(&self) -> u32 {
+        let mut drv_status = self.registers[Registers::DRV_STATUS as usize] & !STANDSTILL;
+        if self.velocity == 0 {
+            drv_status |= STANDSTILL;
+        }
+        drv_status
+    }
+
+    fn spi_status(&self) -> u8 {
+        let gstat = self.registers[Registers::GSTAT as usize];
+        let ramp_stat = self.ramp_stat();
+        let drv_status = self.drv_status();
+        let mut status = (gstat & 0x03) as u8;
+        if drv_status & 1 << 24 != 0 {
+            status |= 1 << 2;
+        }
+        if drv_status & STANDSTILL != 0 {
+            status |= 1 << 3;
+        }
+        if ramp_stat & VELOCITY_REACHED != 0 {
+            status |= 1 << 4;
+        }
+        if ramp_stat & POSITION_REACHED != 0 {
+            status |= 1 << 5;
+        }
+        status
+    }
+
+    fn read(&self, addr: u8) -> u32 {
+        match addr {
+            a if a == Registers::RAMP_STAT as u8 => self.ramp_stat(),
+            a if a == Registers::DRV_STATUS as u8 => self.drv_status(),
+            a if a == Registers::VACTUAL as u8 => self.velocity as u32 & 0xFF_FFFF,
+            a => self.registers[a as usize & 0x7F],
+        }
+    }
+
+    fn write(&mut self, addr: u8, val: u32) {
+        let reg = &mut self.registers[addr as usize];
+        match addr {
+            // write 1 to clear
+            a if a == Registers::GSTAT as u8 || a == Registers::ENC_STATUS as u8 => *reg &= !val,
+            a if a == Registers::RAMP_STAT as u8 => *reg &= !(val & RAMP_STAT_EVENTS),
+            _ => *reg = val,
+        }
+    }
+
+    fn datagram(&mut self, words: &mut [u8]) {
+        if words.len() != 5 {
+            return;
+        }
+        let addr = words[0];
+        let val = u32::from_be_bytes([words[1], words[2], words[3], words[4]]);
+        let status = self.spi_status();
+        let reply = self.read(self.read_addr);
+        if addr & 0x80 != 0 {
+            self.write(addr & 0x7F, val);
+        } else {
+            self.read_addr = addr & 0x7F;
+        }
+        words[0] = status;
+        words[1..].copy_from_slice(&reply.to_be_bytes());
+    }
+}
+
+impl Transfer<u8> for Tmc5160Sim {
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.datagram(words);
+        Ok(words)
+    }
+}
+
+impl Write<u8> for Tmc5160Sim {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let mut buffer = [0; 5];
+        if words.len() == buffer.len() {
+            buffer.copy_from_slice(words);
+            self.datagram(&mut buffer);
+        }
+        Ok(())
+    }
+}
+
+/// Pin that does nothing, for the CS and EN pins of a simulated driver
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SimPin;
+
+impl OutputPin for SimPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Driver without shadow registers
+//!
+//! For targets with very little RAM, `Tmc5160Slim` can be used instead of `Tmc5160`. It only keeps
+//! the bus, the pins and the last SPI status. The configuration is written and read with
+//! `write_register()` and `read_register()`, using the register structs of the `registers` module
+//! to build the values.
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+
+use crate::chip::CHIP;
+use crate::registers::{Address, SpiStatus};
+use crate::{DataPacket, Error};
+
+/// TMC5160 driver without shadow registers
+pub struct Tmc5160Slim<SPI, CS, EN> {
+    spi: SPI,
+    cs: CS,
+    en: Option<EN>,
+    en_inverted: bool,
+    /// status register of the driver
+    pub status: SpiStatus,
+}
+
+impl<SPI, CS, EN, E> Tmc5160Slim<SPI, CS, EN>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin,
+    EN: OutputPin,
+{
+    /// Create a new driver from a SPI peripheral and a NCS pin
+    pub fn new(spi: SPI, cs: CS) -> Self {
+        Tmc5160Slim {
+            spi,
+            cs,
+            en: None,
+            en_inverted: false,
+            status: SpiStatus::new(),
+        }
+    }
+
+    /// attach an enable pin to the driver
+    pub fn attach_en(mut self, en: EN) -> Self {
+        self.en = Some(en);
+        self
+    }
+
+    /// invert the enable pin
+    pub fn en_inverted(mut self, inv: bool) -> Self {
+        self.en_inverted = inv;
+        self
+    }
+
+    /// get a mutable reference to the SPI bus
+    pub fn spi_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    /// read a specified register
+    pub fn read_register<T>(&mut self, reg: T) -> Result<DataPacket, Error<E>>
+    where
+        T: Address + Copy,
+    {
+        // the reply to a read request arrives with the next datagram
+        self.transfer([reg.addr(), 0, 0, 0, 0], reg.addr())?;
+        self.transfer([reg.addr(), 0, 0, 0, 0], reg.addr())
+    }
+
+    /// write value to a specified register
+    pub fn write_register<T>(&mut self, reg: T, val: &mut [u8; 4]) -> Result<DataPacket, Error<E>>
+    where
+        T: Address + Copy,
+    {
+        self.transfer(
+            [reg.addr() | 0x80, val[0], val[1], val[2], val[3]],
+            reg.addr(),
+        )
+    }
+
+    fn transfer(&mut self, datagram: [u8; 5], addr: u8) -> Result<DataPacket, Error<E>> {
+        if !CHIP.has_register(addr) {
+            return Err(Error::Unsupported);
+        }
+        let mut buffer = datagram;
+        self.cs.set_low().ok();
+        let response = self.spi.transfer(&mut buffer);
+        self.cs.set_high().ok();
+        let [status, a, b, c, d] = match response.map_err(Error::Spi)? {
+            [status, a, b, c, d] => [*status, *a, *b, *c, *d],
+            _ => return Err(Error::Format),
+        };
+        self.status = SpiStatus::from_bytes([status]);
+        Ok(DataPacket {
+            status: self.status,
+            data: u32::from_be_bytes([a, b, c, d]),
+            debug: datagram,
+        })
+    }
+
+    /// enable the motor if the EN pin was specified
+    pub fn enable(&mut self) -> Result<(), Error<E>> {
+        let inverted = self.en_inverted;
+        self.set_en(inverted)
+    }
+
+    /// disable the motor if the EN pin was specified
+    pub fn disable(&mut self) -> Result<(), Error<E>> {
+        let inverted = self.en_inverted;
+        self.set_en(!inverted)
+    }
+
+    fn set_en(&mut self, high: bool) -> Result<(), Error<E>> {
+        match &mut self.en {
+            Some(pin) if high => pin.set_high().map_err(|_| Error::PinError),
+            Some(pin) => pin.set_low().map_err(|_| Error::PinError),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! Split of the driver into a motion command handle and a telemetry handle
+//!
+//! Both handles share the driver through a [`DriverMutex`], which is only locked for the duration
+//! of a single driver call. This allows a high priority control task and a low priority logger
+//! (e.g. RTIC tasks) to use the same chip without holding a lock across the whole task.
+use core::cell::RefCell;
+use core::sync::atomic::{fence, AtomicU32, Ordering};
+
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+
+use crate::registers::*;
+use crate::{DataPacket, Error, Tmc5160};
+
+/// Mutex providing exclusive access to a driver
+pub trait DriverMutex {
+    /// the protected driver
+    type Driver;
+
+    /// run `f` with exclusive access to the driver
+    fn lock<R>(&self, f: impl FnOnce(&mut Self::Driver) -> R) -> R;
+}
+
+/// `RefCell` based mutex for drivers shared within a single execution context
+impl<T> DriverMutex for RefCell<T> {
+    type Driver = T;
+
+    fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.borrow_mut())
+    }
+}
+
+/// Driver protected by a critical section, for sharing between thread mode and interrupts
+///
+/// Can be placed in a `static` and used through `split()` or `DriverMutex::lock()` from both the
+/// main loop and e.g. the interrupt handler of a DIAG pin.
+#[cfg(feature = "critical-section")]
+pub struct CsDriver<T> {
+    inner: critical_section::Mutex<RefCell<T>>,
+}
+
+#[cfg(feature = "critical-section")]
+impl<T> CsDriver<T> {
+    /// wrap a driver
+    pub const fn new(driver: T) -> Self {
+        CsDriver {
+            inner: critical_section::Mutex::new(RefCell::new(driver)),
+        }
+    }
+
+    /// unwrap the driver
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner().into_inner()
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<T> DriverMutex for CsDriver<T> {
+    type Driver = T;
+
+    fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.inner.borrow_ref_mut(cs)))
+    }
+}
+
+/// Access to a driver, either directly or through a handle
+///
+/// Implemented by `Tmc5160` itself and by `CommandHandle`, so the helper subsystems (see the `load`
+/// and `fault` modules) only borrow the driver for a single call and work with either.
+pub trait DriverAccess {
+    /// the accessed driver
+    type Driver;
+
+    /// run `f` with exclusive access to the driver
+    fn access<R>(&mut self, f: impl FnOnce(&mut Self::Driver) -> R) -> R;
+}
+
+impl<SPI, CS, EN> DriverAccess for Tmc5160<SPI, CS, EN> {
+    type Driver = Self;
+
+    fn access<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        f(self)
+    }
+}
+
+impl<M: DriverMutex> DriverAccess for CommandHandle<'_, M> {
+    type Driver = M::Driver;
+
+    fn access<R>(&mut self, f: impl FnOnce(&mut M::Driver) -> R) -> R {
+        self.driver.lock(f)
+    }
+}
+
+/// split a shared driver into a motion command handle and a telemetry handle
+pub fn split<M: DriverMutex>(driver: &M) -> (CommandHandle<'_, M>, TelemetryHandle<'_, M>) {
+    (CommandHandle { driver }, TelemetryHandle { driver })
+}
+
+/// Handle for motion commands
+pub struct CommandHandle<'a, M> {
+    driver: &'a M,
+}
+
+/// Read-only handle for telemetry
+pub struct TelemetryHandle<'a, M> {
+    driver: &'a M,
+}
+
+// implemented by hand, a derive would require `M: Clone`
+impl<M> Clone for TelemetryHandle<'_, M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M> Copy for TelemetryHandle<'_, M> {}
+
+impl<M, SPI, CS, EN, E> CommandHandle<'_, M>
+where
+    M: DriverMutex<Driver = Tmc5160<SPI, CS, EN>>,
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin,
+    EN: OutputPin,
+{
+    /// run `f` with exclusive access to the driver for operations not covered by the handle
+    pub fn with_driver<R>(&mut self, f: impl FnOnce(&mut Tmc5160<SPI, CS, EN>) -> R) -> R {
+        self.driver.lock(f)
+    }
+
+    /// enable the motor if the EN pin was specified
+    pub fn enable(&mut self) -> Result<(), Error<E>> {
+        self.driver.lock(|driver| driver.enable())
+    }
+
+    /// disable the motor if the EN pin was specified
+    pub fn disable(&mut self) -> Result<(), Error<E>> {
+        self.driver.lock(|driver| driver.disable())
+    }
+
+    /// move to a specific location
+    pub fn move_to(&mut self, target: f32) -> Result<DataPacket, Error<E>> {
+        self.driver.lock(|driver| driver.move_to(target))
+    }
+
+    /// set the max velocity (VMAX)
+    pub fn set_velocity(&mut self, velocity: f32) -> Result<DataPacket, Error<E>> {
+        self.driver.lock(|driver| driver.set_velocity(velocity))
+    }
+
+    /// set the max acceleration (AMAX, A1)
+    pub fn set_acceleration(&mut self, acceleration: f32) -> Result<DataPacket, Error<E>> {
+        self.driver
+            .lock(|driver| driver.set_acceleration(acceleration))
+    }
+
+    /// set the max deceleration (DMAX, D1)
+    pub fn set_deceleration(&mut self, deceleration: f32) -> Result<DataPacket, Error<E>> {
+        self.driver
+            .lock(|driver| driver.set_deceleration(deceleration))
+    }
+
+    /// write value to RAMPMODE register
+    pub fn set_rampmode(&mut self, mode: RampMode) -> Result<DataPacket, Error<E>> {
+        self.driver.lock(|driver| driver.set_rampmode(mode))
+    }
+
+    /// stop the motor now
+    pub fn stop(&mut self) -> Result<DataPacket, Error<E>> {
+        self.driver.lock(|driver| driver.stop())
+    }
+
+    /// set the position to 0 / home
+    pub fn set_home(&mut self) -> Result<DataPacket, Error<E>> {
+        self.driver.lock(|driver| driver.set_home())
+    }
+
+    /// stop as fast as possible and latch the emergency stop
+    pub fn emergency_stop(&mut self, disable_stage: bool) -> Result<DataPacket, Error<E>> {
+        self.driver
+            .lock(|driver| driver.emergency_stop(disable_stage))
+    }
+
+    /// re-arm the driver after an emergency stop
+    pub fn clear_emergency_stop(&mut self) -> Result<(), Error<E>> {
+        self.driver.lock(|driver| driver.clear_emergency_stop())
+    }
+}
+
+impl<M, SPI, CS, EN, E> TelemetryHandle<'_, M>
+where
+    M: DriverMutex<Driver = Tmc5160<SPI, CS, EN>>,
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin,
+    EN: OutputPin,
+{
+    /// get the current position
+    pub fn get_position(&self) -> Result<f32, Error<E>> {
+        self.driver.lock(|driver| driver.get_position())
+    }
+
+    /// get the current velocity
+    pub fn get_velocity(&self) -> Result<f32, Error<E>> {
+        self.driver.lock(|driver| driver.get_velocity())
+    }
+
+    /// get the current target position (XTARGET)
+    pub fn get_target(&self) -> Result<f32, Error<E>> {
+        self.driver.lock(|driver| driver.get_target())
+    }
+
+    /// check if the motor is moving
+    pub fn is_moving(&self) -> Result<bool, Error<E>> {
+        self.driver.lock(|driver| driver.is_moving())
+    }
+
+    /// read DRV_STATUS register
+    pub fn read_drv_status(&self) -> Result<DrvStatus, Error<E>> {
+        self.driver.lock(|driver| driver.read_drv_status())
+    }
+
+    /// read RAMP_STAT register
+    pub fn read_ramp_status(&self) -> Result<RampStat, Error<E>> {
+        self.driver.lock(|driver| driver.read_ramp_status())
+    }
+
+    /// read GSTAT register
+    pub fn read_gstat(&self) -> Result<GStat, Error<E>> {
+        self.driver.lock(|driver| driver.read_gstat())
+    }
+
+    /// read PWM_SCALE register
+    pub fn read_pwm_scale(&self) -> Result<PwmScale, Error<E>> {
+        self.driver.lock(|driver| driver.read_pwm_scale())
+    }
+
+    /// status of the last transfer
+    pub fn status(&self) -> SpiStatus {
+        self.driver.lock(|driver| driver.status)
+    }
+}
+
+/// Status of the last transactions, published by the driver for interrupts and watchdog tasks
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct CachedStatus {
+    /// SPI status of the last transfer
+    pub status: SpiStatus,
+    /// RAMP_STAT of the last read, flags cleared by a write are removed
+    pub ramp_stat: RampStat,
+    /// XACTUAL of the last read or write
+    pub xactual: i32,
+}
+
+struct CacheSlot {
+    sequence: AtomicU32,
+    status: AtomicU32,
+    ramp_stat: AtomicU32,
+    xactual: AtomicU32,
+}
+
+impl CacheSlot {
+    const fn new() -> Self {
+        CacheSlot {
+            sequence: AtomicU32::new(0),
+            status: AtomicU32::new(0),
+            ramp_stat: AtomicU32::new(0),
+            xactual: AtomicU32::new(0),
+        }
+    }
+}
+
+/// Lock-free cache of the latest status, readable without taking the SPI bus
+///
+/// Attached to a driver with `Tmc5160::set_status_cache()`, the driver updates it with every
+/// transaction, so an interrupt handler or a watchdog task can check the state of the motor with
+/// `load()`, e.g. in a `static`. The driver writes the inactive of two slots and then switches to
+/// it, so a reader interrupting the driver always sees a consistent snapshot, a reader interrupted
+/// by the driver retries.
+pub struct StatusCache {
+    active: AtomicU32,
+    slots: [CacheSlot; 2],
+}
+
+impl StatusCache {
+    /// create an empty cache
+    pub const fn new() -> Self {
+        StatusCache {
+            active: AtomicU32::new(0),
+            slots: [CacheSlot::new(), CacheSlot::new()],
+        }
+    }
+
+    /// latest published status
+    pub fn load(&self) -> CachedStatus {
+        loop {
+            let slot = &self.slots[(self.active.load(Ordering::Acquire) & 1) as usize];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            if sequence & 1 != 0 {
+                continue;
+            }
+            let value = CachedStatus {
+                status: SpiStatus::from_bytes([slot.status.load(Ordering::Relaxed) as u8]),
+                ramp_stat: RampStat::from_bytes(
+                    slot.ramp_stat.load(Ordering::Relaxed).to_le_bytes(),
+                ),
+                xactual: slot.xactual.load(Ordering::Relaxed) as i32,
+            };
+            fence(Ordering::Acquire);
+            if slot.sequence.load(Ordering::Relaxed) == sequence {
+                return value;
+            }
+        }
+    }
+
+    /// publish a new status, only called by the driver owning the cache
+    pub(crate) fn update(&self, f: impl FnOnce(&mut CachedStatus)) {
+        let mut value = self.load();
+        f(&mut value);
+        let active = self.active.load(Ordering::Relaxed).wrapping_add(1);
+        let slot = &self.slots[(active & 1) as usize];
+        let sequence = slot.sequence.load(Ordering::Relaxed);
+        slot.sequence
+            .store(sequence.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        slot.status
+            .store(value.status.into_bytes()[0] as u32, Ordering::Relaxed);
+        slot.ramp_stat.store(
+            u32::from_le_bytes(value.ramp_stat.into_bytes()),
+            Ordering::Relaxed,
+        );
+        slot.xactual.store(value.xactual as u32, Ordering::Relaxed);
+        slot.sequence
+            .store(sequence.wrapping_add(2), Ordering::Release);
+        self.active.store(active, Ordering::Release);
+    }
+}
+
+impl Default for StatusCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
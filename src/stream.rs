@@ -0,0 +1,111 @@
+//! Streaming of trajectory points from an external planner
+use embedded_hal::{
+    blocking::spi::{Transfer, Write},
+    digital::v2::OutputPin,
+};
+
+use crate::conversions::velocity_to_register;
+use crate::registers::{RampMode, Registers};
+use crate::split::DriverAccess;
+use crate::{checked_register, Error, Tmc5160, VMAX_LIMIT};
+
+/// Streams trajectory points at a fixed period in positioning mode
+///
+/// Each `push()` gives the position in microsteps the axis should reach one period later. VMAX is
+/// set to the velocity needed to get from the actual position to the point within one period, and
+/// XTARGET is extrapolated by one more segment, so the ramp generator never starts decelerating
+/// towards an intermediate point. `finish()` writes the last point as the final target, where the
+/// axis stops with the normal deceleration ramp.
+/// AMAX and DMAX have to allow the velocity changes between consecutive segments.
+#[derive(Debug, Copy, Clone)]
+pub struct TrajectoryStream {
+    /// time between two points in µs
+    pub period_us: u32,
+    last: Option<i32>,
+}
+
+impl TrajectoryStream {
+    /// create a stream with `period_us` between two points
+    pub fn new(period_us: u32) -> Self {
+        TrajectoryStream {
+            period_us,
+            last: None,
+        }
+    }
+
+    /// last pushed point, or the start position
+    pub fn last_point(&self) -> Option<i32> {
+        self.last
+    }
+
+    /// switch to positioning mode and start a stream at the actual position
+    pub fn start<A, SPI, CS, EN, E>(&mut self, driver: &mut A) -> Result<(), Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        driver.access(|driver| {
+            driver.set_rampmode(RampMode::PositioningMode)?;
+            self.last = Some(driver.read_register(Registers::XACTUAL)?.data as i32);
+            Ok(())
+        })
+    }
+
+    /// push the next trajectory point in microsteps
+    pub fn push<A, SPI, CS, EN, E>(&mut self, driver: &mut A, target: i32) -> Result<(), Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        driver.access(|driver| self.push_driver(driver, target))
+    }
+
+    fn push_driver<SPI, CS, EN, E>(
+        &mut self,
+        driver: &mut Tmc5160<SPI, CS, EN>,
+        target: i32,
+    ) -> Result<(), Error<E>>
+    where
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        if self.period_us == 0 {
+            return Err(Error::InvalidValue);
+        }
+        let actual = driver.read_register(Registers::XACTUAL)?.data as i32;
+        let last = self.last.unwrap_or(actual);
+        // the velocity is based on the actual position to catch up with lag
+        let distance = (target as i64 - actual as i64).unsigned_abs();
+        let velocity = distance as f32 * 1_000_000.0 / self.period_us as f32;
+        let v_max = checked_register(velocity_to_register(velocity, driver._clock), VMAX_LIMIT)
+            .ok_or(Error::InvalidValue)?;
+        let delta = target as i64 - last as i64;
+        let extrapolated = (target as i64 + delta).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+
+        driver.set_velocity_raw(v_max)?;
+        driver.write_register(Registers::XTARGET, &mut extrapolated.to_be_bytes())?;
+        self.last = Some(target);
+        Ok(())
+    }
+
+    /// end the stream, the axis stops at the last pushed point
+    pub fn finish<A, SPI, CS, EN, E>(&mut self, driver: &mut A) -> Result<(), Error<E>>
+    where
+        A: DriverAccess<Driver = Tmc5160<SPI, CS, EN>>,
+        SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+        CS: OutputPin,
+        EN: OutputPin,
+    {
+        if let Some(last) = self.last.take() {
+            driver.access(|driver| {
+                driver.write_register(Registers::XTARGET, &mut last.to_be_bytes())
+            })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+//! Datagrams of the single wire UART interface
+//!
+//! With SPI_MODE = 0 and SD_MODE = 0 the chip is controlled through UART instead of SPI. A write
+//! datagram has 8 bytes (sync, node address, register | 0x80, 4 data bytes, CRC), a read request 4
+//! bytes (sync, node address, register, CRC). The chip answers a read request with an 8 byte
+//! reply datagram addressed to the master (0xFF).
+//!
+//! `UartTransport` sends the datagrams over a serial port, validates the CRC of the replies and
+//! repeats a read whose reply is corrupted. Several chips with different node addresses can share
+//! one serial port through a `UartBus`.
+use embedded_hal::{
+    blocking::{delay::DelayUs, serial::Write},
+    serial::Read,
+};
+
+use crate::deadline::Deadline;
+use crate::registers::Address;
+use crate::registers::{Registers, SendDelay};
+use crate::split::DriverMutex;
+
+/// sync byte of all datagrams (the upper nibble is reserved and ignored in replies)
+pub const SYNC: u8 = 0x05;
+/// address of the master in reply datagrams
+pub const MASTER_ADDRESS: u8 = 0xFF;
+
+/// CRC8 of a datagram (polynomial x^8 + x^2 + x + 1, bytes processed LSB first)
+///
+/// ```
+/// use tmc5160::uart::crc8;
+/// // read request of register 0x06 from node 0
+/// assert_eq!(crc8(&[0x05, 0x00, 0x06]), 0x6F);
+/// ```
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0_u8;
+    for byte in data {
+        let mut byte = *byte;
+        for _ in 0..8 {
+            if (crc >> 7) ^ (byte & 0x01) != 0 {
+                crc = (crc << 1) ^ 0x07;
+            } else {
+                crc <<= 1;
+            }
+            byte >>= 1;
+        }
+    }
+    crc
+}
+
+/// datagram writing `val` to `reg` of the chip with the address `node`
+pub fn write_datagram<T: Address>(node: u8, reg: T, val: u32) -> [u8; 8] {
+    let [a, b, c, d] = val.to_be_bytes();
+    let mut datagram = [SYNC, node, reg.addr() | 0x80, a, b, c, d, 0];
+    datagram[7] = crc8(&datagram[..7]);
+    datagram
+}
+
+/// datagram requesting the value of `reg` from the chip with the address `node`
+pub fn read_request<T: Address>(node: u8, reg: T) -> [u8; 4] {
+    let mut datagram = [SYNC, node, reg.addr(), 0];
+    datagram[3] = crc8(&datagram[..3]);
+    datagram
+}
+
+/// CRC of a reply datagram that does not match its content
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CrcError {
+    /// CRC calculated from the received bytes
+    pub expected: u8,
+    /// CRC byte received
+    pub received: u8,
+}
+
+/// Error of a reply datagram
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReplyError {
+    /// the CRC does not match
+    Crc(CrcError),
+    /// wrong sync byte, address or register
+    Format,
+}
+
+/// check a reply datagram to a read request of `reg` and return the register value
+pub fn parse_reply<T: Address>(reply: &[u8; 8], reg: T) -> Result<u32, ReplyError> {
+    let expected = crc8(&reply[..7]);
+    if expected != reply[7] {
+        return Err(ReplyError::Crc(CrcError {
+            expected,
+            received: reply[7],
+        }));
+    }
+    if reply[0] & 0x0F != SYNC || reply[1] != MASTER_ADDRESS || reply[2] != reg.addr() {
+        return Err(ReplyError::Format);
+    }
+    Ok(u32::from_be_bytes([reply[3], reply[4], reply[5], reply[6]]))
+}
+
+/// Error of the UART transport
+#[derive(Debug)]
+pub enum UartError<E> {
+    /// serial port error
+    Serial(E),
+    /// every attempt of a read was answered with a corrupted reply
+    Crc(CrcError),
+    /// the reply does not belong to the request
+    Format,
+    /// no complete reply was received before the deadline
+    Timeout,
+    /// invalid node address or send delay
+    InvalidValue,
+}
+
+/// Delay that does not wait, used by `UartTransport` without turnaround time
+pub struct NoDelay;
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+/// Register access over a serial port
+pub struct UartTransport<S, D = NoDelay> {
+    serial: S,
+    delay: D,
+    turnaround_us: u32,
+    retries: u8,
+    echo: bool,
+}
+
+impl<S, E> UartTransport<S>
+where
+    S: Read<u8, Error = E> + Write<u8, Error = E>,
+{
+    /// create a transport on `serial`, a corrupted read is repeated up to 3 times
+    pub fn new(serial: S) -> Self {
+        UartTransport {
+            serial,
+            delay: NoDelay,
+            turnaround_us: 0,
+            retries: 3,
+            echo: false,
+        }
+    }
+}
+
+impl<S, D, E> UartTransport<S, D>
+where
+    S: Read<u8, Error = E> + Write<u8, Error = E>,
+    D: DelayUs<u32>,
+{
+    /// keep the bus idle for `turnaround_us` before every datagram
+    ///
+    /// On a bus with several nodes, this gives the last replying node time to release the line
+    /// before the next request, a few bit times are sufficient.
+    pub fn turnaround<D2: DelayUs<u32>>(
+        self,
+        delay: D2,
+        turnaround_us: u32,
+    ) -> UartTransport<S, D2> {
+        UartTransport {
+            serial: self.serial,
+            delay,
+            turnaround_us,
+            retries: self.retries,
+            echo: self.echo,
+        }
+    }
+
+    /// number of repetitions of a read after a CRC error (Default is 3)
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// discard the echo of the sent bytes, for RX and TX joined on the single wire bus
+    pub fn echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    /// release the serial port
+    pub fn free(self) -> S {
+        self.serial
+    }
+
+    /// write `val` to `reg` of the chip with the address `node`
+    ///
+    /// Writes are not acknowledged, IFCNT counts the successfully received write datagrams.
+    pub fn write_register<T: Address>(
+        &mut self,
+        node: u8,
+        reg: T,
+        val: u32,
+        deadline: &mut impl Deadline,
+    ) -> Result<(), UartError<E>> {
+        let datagram = write_datagram(node, reg, val);
+        self.send(&datagram, deadline)
+    }
+
+    /// read `reg` of the chip with the address `node`
+    ///
+    /// A reply with a CRC error is requested again, after the last retry the CRC error is
+    /// returned. The deadline applies to the whole read including the retries.
+    pub fn read_register<T: Address + Copy>(
+        &mut self,
+        node: u8,
+        reg: T,
+        deadline: &mut impl Deadline,
+    ) -> Result<u32, UartError<E>> {
+        let request = read_request(node, reg);
+        let mut attempts = 0;
+        loop {
+            self.send(&request, deadline)?;
+            let mut reply = [0; 8];
+            self.receive(&mut reply, deadline)?;
+            match parse_reply(&reply, reg) {
+                Ok(value) => return Ok(value),
+                Err(ReplyError::Crc(crc)) if attempts >= self.retries => {
+                    return Err(UartError::Crc(crc))
+                }
+                Err(ReplyError::Crc(_)) => attempts += 1,
+                Err(ReplyError::Format) => return Err(UartError::Format),
+            }
+        }
+    }
+
+    fn send(&mut self, datagram: &[u8], deadline: &mut impl Deadline) -> Result<(), UartError<E>> {
+        if self.turnaround_us > 0 {
+            self.delay.delay_us(self.turnaround_us);
+        }
+        self.serial
+            .bwrite_all(datagram)
+            .map_err(UartError::Serial)?;
+        self.serial.bflush().map_err(UartError::Serial)?;
+        if self.echo {
+            let mut echo = [0; 8];
+            self.receive(&mut echo[..datagram.len()], deadline)?;
+        }
+        Ok(())
+    }
+
+    fn receive(
+        &mut self,
+        buffer: &mut [u8],
+        deadline: &mut impl Deadline,
+    ) -> Result<(), UartError<E>> {
+        for byte in buffer.iter_mut() {
+            *byte = loop {
+                match self.serial.read() {
+                    Ok(byte) => break byte,
+                    Err(nb::Error::WouldBlock) => {
+                        if deadline.expired() {
+                            return Err(UartError::Timeout);
+                        }
+                        deadline.wait();
+                    }
+                    Err(nb::Error::Other(e)) => return Err(UartError::Serial(e)),
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Serial bus shared by several chips
+///
+/// The transport is protected by a [`DriverMutex`], e.g. a `RefCell` or a `CsDriver`, which is
+/// locked for each register access, so the accesses of the nodes never interleave.
+pub struct UartBus<M> {
+    mutex: M,
+}
+
+impl<M, S, D, E> UartBus<M>
+where
+    M: DriverMutex<Driver = UartTransport<S, D>>,
+    S: Read<u8, Error = E> + Write<u8, Error = E>,
+    D: DelayUs<u32>,
+{
+    /// create a bus on a protected transport
+    pub fn new(mutex: M) -> Self {
+        UartBus { mutex }
+    }
+
+    /// access the chip with the node address `address`
+    pub fn node(&self, address: u8) -> UartNode<'_, M> {
+        UartNode { bus: self, address }
+    }
+
+    /// release the protected transport
+    pub fn into_inner(self) -> M {
+        self.mutex
+    }
+}
+
+/// Chip on a shared UART bus
+pub struct UartNode<'a, M> {
+    bus: &'a UartBus<M>,
+    address: u8,
+}
+
+impl<M, S, D, E> UartNode<'_, M>
+where
+    M: DriverMutex<Driver = UartTransport<S, D>>,
+    S: Read<u8, Error = E> + Write<u8, Error = E>,
+    D: DelayUs<u32>,
+{
+    /// node address of the chip
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// read `reg` of the chip
+    pub fn read_register<T: Address + Copy>(
+        &self,
+        reg: T,
+        deadline: &mut impl Deadline,
+    ) -> Result<u32, UartError<E>> {
+        self.bus
+            .mutex
+            .lock(|transport| transport.read_register(self.address, reg, deadline))
+    }
+
+    /// write `val` to `reg` of the chip
+    pub fn write_register<T: Address>(
+        &self,
+        reg: T,
+        val: u32,
+        deadline: &mut impl Deadline,
+    ) -> Result<(), UartError<E>> {
+        self.bus
+            .mutex
+            .lock(|transport| transport.write_register(self.address, reg, val, deadline))
+    }
+
+    /// move the chip to the node address `address` (0 to 253) and set its send delay
+    ///
+    /// Writes SLAVECONF with the current address. The send delay has to be at least 24 bit times,
+    /// as other nodes share the bus.
+    pub fn configure(
+        &mut self,
+        address: u8,
+        send_delay: SendDelay,
+        deadline: &mut impl Deadline,
+    ) -> Result<(), UartError<E>> {
+        if address > 253 || !send_delay.is_multi_drop_safe() {
+            return Err(UartError::InvalidValue);
+        }
+        let value = address as u32 | (send_delay as u32) << 8;
+        self.write_register(Registers::SLAVECONF, value, deadline)?;
+        self.address = address;
+        Ok(())
+    }
+}
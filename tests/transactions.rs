@@ -0,0 +1,976 @@
+//! Transaction level tests of the driver against `embedded-hal-mock`
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal_mock::pin::{Mock as PinMock, State, Transaction as PinTransaction};
+use tmc5160::correction::{CorrectionPolicy, PositionCorrection};
+use tmc5160::fault::{DisconnectMonitor, FaultFlags, HealthMonitor, SaturationMonitor};
+use tmc5160::load::LoadTracker;
+use tmc5160::mock::Expectations;
+use tmc5160::registers::{
+    DrvStatus, EncMode, EncoderClear, FreeWheel, NEventSensitivity, RampMode, Register, Registers,
+};
+use tmc5160::slim::Tmc5160Slim;
+use tmc5160::split::{split, StatusCache};
+use tmc5160::{
+    EnableRamp, Error, HoldPercent, ReadCache, ReservedBits, RmsCurrent, SpeedProfile, TickDuties,
+    TickDuty,
+};
+
+#[test]
+fn write_register_datagram() {
+    let mut mocks = Expectations::new()
+        .write(Registers::VMAX, 0x0001_86A0)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_vmax(100_000).unwrap();
+    mocks.done();
+}
+
+#[test]
+fn read_register_sends_dummy_datagram() {
+    let mut mocks = Expectations::new()
+        .read(Registers::XACTUAL, (-512_i32) as u32)
+        .build();
+    let mut driver = mocks.driver();
+    assert_eq!(driver.get_position().unwrap(), -2.0);
+    mocks.done();
+}
+
+#[test]
+fn g_conf_bit_layout() {
+    let mut mocks = Expectations::new()
+        .write(Registers::GCONF, 0x0000_0014)
+        .build();
+    let mut driver = mocks.driver();
+    driver.g_conf.set_en_pwm_mode(true);
+    driver.set_shaft(true).unwrap();
+    mocks.done();
+}
+
+#[test]
+fn ihold_irun_bit_layout() {
+    let mut mocks = Expectations::new()
+        .write(Registers::IHOLD_IRUN, 0x0006_1008)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_currents(16, 8, 6).unwrap();
+    mocks.done();
+}
+
+#[test]
+fn move_to_arms_target_reached() {
+    let mut mocks = Expectations::new()
+        .read(Registers::XACTUAL, 0)
+        .write(Registers::RAMP_STAT, 0x0000_0080)
+        .write(Registers::XTARGET, 0x0000_0200)
+        .build();
+    let mut driver = mocks.driver();
+    driver.move_to(2.0).unwrap();
+    mocks.done();
+}
+
+#[test]
+fn move_to_actual_position_completes_at_once() {
+    let mut mocks = Expectations::new()
+        .status(0x20)
+        .read(Registers::XACTUAL, 0x0000_0200)
+        .status(0)
+        .write(Registers::RAMP_STAT, 0x0000_0080)
+        .write(Registers::XTARGET, 0x0000_0200)
+        .build();
+    let mut driver = mocks.driver();
+    driver.move_to(2.0).unwrap();
+    assert!(driver.target_reached().unwrap());
+    assert!(!driver.target_reached().unwrap());
+    mocks.done();
+}
+
+#[test]
+fn drv_status_decoding() {
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0x8000_0123)
+        .build();
+    let mut driver = mocks.driver();
+    let drv_status = driver.read_drv_status().unwrap();
+    assert!(drv_status.standstill());
+    assert_eq!(drv_status.sg_result(), 0x123);
+    mocks.done();
+}
+
+#[test]
+fn rampmode_read_back() {
+    let mut mocks = Expectations::new()
+        .write(Registers::RAMPMODE, 3)
+        .read(Registers::RAMPMODE, 3)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_rampmode(RampMode::HoldMode).unwrap();
+    assert_eq!(driver.read_rampmode().unwrap(), RampMode::HoldMode);
+    mocks.done();
+}
+
+#[test]
+fn strict_status_reports_reset() {
+    let mut mocks = Expectations::new()
+        .status(0x01)
+        .write(Registers::VMAX, 0)
+        .build();
+    let mut driver = mocks.driver().strict_status(true);
+    assert!(matches!(driver.set_vmax(0), Err(Error::Status(_))));
+    mocks.done();
+}
+
+#[test]
+fn invalid_value_sends_nothing() {
+    let mut mocks = Expectations::new().build();
+    let mut driver = mocks.driver();
+    assert!(matches!(
+        driver.set_currents(32, 0, 0),
+        Err(Error::InvalidValue)
+    ));
+    mocks.done();
+}
+
+#[test]
+fn ramp_register_limits() {
+    let mut mocks = Expectations::new()
+        .write(Registers::VMAX, (1 << 23) - 512)
+        .build();
+    let mut driver = mocks.driver();
+    assert!(matches!(driver.set_vmax(1 << 23), Err(Error::InvalidValue)));
+    assert!(matches!(driver.set_vstop(5), Err(Error::InvalidValue)));
+    assert!(matches!(driver.set_d1(0), Err(Error::InvalidValue)));
+    driver.set_vmax((1 << 23) - 512).unwrap();
+    mocks.done();
+}
+
+#[test]
+fn emergency_stop_rejects_motion() {
+    let mut mocks = Expectations::new()
+        .write(Registers::AMAX, 0xFFFF)
+        .write(Registers::VMAX, 0)
+        .read(Registers::RAMPMODE, 0)
+        .read(Registers::XTARGET, 5000)
+        .write(Registers::RAMPMODE, 1)
+        .build();
+    let mut driver = mocks.driver();
+    driver.emergency_stop(false).unwrap();
+    assert!(matches!(driver.move_to(10.0), Err(Error::EmergencyStop)));
+    assert!(matches!(
+        driver.set_velocity(1.0),
+        Err(Error::EmergencyStop)
+    ));
+    mocks.done();
+}
+
+#[test]
+fn emergency_stop_recovery_resumes_target() {
+    let mut mocks = Expectations::new()
+        .write(Registers::AMAX, 0xFFFF)
+        .write(Registers::VMAX, 0)
+        .read(Registers::RAMPMODE, 0)
+        .read(Registers::XTARGET, 5000)
+        .write(Registers::RAMPMODE, 1)
+        // clear_emergency_stop() checks for standstill
+        .read(Registers::RAMP_STAT, 0x400)
+        .read(Registers::DRV_STATUS, 0x8000_0000)
+        .write(Registers::AMAX, 500)
+        .read(Registers::XACTUAL, 1200)
+        .write(Registers::XTARGET, 1200)
+        .write(Registers::RAMPMODE, 0)
+        .write(Registers::AMAX, 500)
+        .write(Registers::IHOLD_IRUN, 0)
+        .write(Registers::VMAX, 0)
+        .write(Registers::RAMP_STAT, 0x80)
+        .write(Registers::XTARGET, 5000)
+        .build();
+    let mut driver = mocks.driver();
+    driver.emergency_stop(false).unwrap();
+    assert!(matches!(
+        driver.recover_from_emergency_stop(true),
+        Err(Error::EmergencyStop)
+    ));
+    driver.clear_emergency_stop().unwrap();
+    // AMAX is still at the maximum of the stop
+    assert!(matches!(
+        driver.recover_from_emergency_stop(true),
+        Err(Error::NotConfigured)
+    ));
+    driver.set_amax(500).unwrap();
+    let position = driver.recover_from_emergency_stop(true).unwrap();
+    assert_eq!(position, 1200.0 / 256.0);
+    mocks.done();
+}
+
+#[test]
+fn backlash_on_reversal() {
+    let mut mocks = Expectations::new()
+        .read(Registers::XACTUAL, 0)
+        .read(Registers::XACTUAL, 0)
+        .write(Registers::RAMP_STAT, 0x80)
+        .write(Registers::XTARGET, 2560)
+        .read(Registers::XACTUAL, 1280)
+        .write(Registers::RAMP_STAT, 0x80)
+        .write(Registers::XTARGET, 1280 - 256)
+        .read(Registers::XACTUAL, 1024)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_backlash(1.0).unwrap();
+    driver.move_to(10.0).unwrap();
+    driver.move_to(5.0).unwrap();
+    assert_eq!(driver.get_position().unwrap(), 5.0);
+    mocks.done();
+}
+
+#[test]
+fn soft_limits() {
+    let mut mocks = Expectations::new()
+        .write(Registers::RAMPMODE, 3)
+        .read(Registers::XACTUAL, 0)
+        .write(Registers::RAMP_STAT, 0x80)
+        .write(Registers::XTARGET, 25600)
+        .write(Registers::RAMPMODE, 0)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_soft_limits(-10.0, 100.0).unwrap();
+    assert!(matches!(driver.move_to(100.5), Err(Error::SoftLimit)));
+    assert!(matches!(
+        driver.set_rampmode(RampMode::VelocityModePos),
+        Err(Error::SoftLimit)
+    ));
+    assert!(matches!(
+        driver.switch_to_velocity(RampMode::VelocityModeNeg),
+        Err(Error::SoftLimit)
+    ));
+    driver.set_rampmode(RampMode::HoldMode).unwrap();
+    driver.run_to_soft_limit(RampMode::VelocityModePos).unwrap();
+    mocks.done();
+}
+
+#[test]
+fn move_to_with_restores_defaults() {
+    let mut mocks = Expectations::new()
+        .write(Registers::VMAX, 1000)
+        .write(Registers::AMAX, 500)
+        .write(Registers::VMAX, 357)
+        .write(Registers::AMAX, 390)
+        .write(Registers::A1, 390)
+        .read(Registers::XACTUAL, 0)
+        .write(Registers::RAMP_STAT, 0x80)
+        .write(Registers::XTARGET, 256)
+        .read(Registers::RAMP_STAT, 0x80)
+        .write(Registers::RAMP_STAT, 0x80)
+        .write(Registers::VMAX, 1000)
+        .write(Registers::AMAX, 500)
+        .write(Registers::A1, 500)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_velocity_raw(1000).unwrap();
+    driver.set_amax(500).unwrap();
+    driver.move_to_with(1.0, 1.0, 100.0).unwrap();
+    assert!(driver.target_reached().unwrap());
+    mocks.done();
+}
+
+#[test]
+fn speed_override_scales_vmax() {
+    let mut mocks = Expectations::new()
+        .write(Registers::VMAX, 1000)
+        .write(Registers::VMAX, 500)
+        .write(Registers::VMAX, 1000)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_velocity_raw(1000).unwrap();
+    driver.set_speed_override_percent(50).unwrap();
+    driver.set_velocity_raw(2000).unwrap();
+    assert_eq!(driver.get_speed_override_percent(), 50);
+    mocks.done();
+}
+
+#[test]
+fn read_cache_until_write() {
+    let mut mocks = Expectations::new()
+        .read(Registers::XACTUAL, 512)
+        .write(Registers::XACTUAL, 0)
+        .read(Registers::XACTUAL, 0)
+        .read(Registers::XACTUAL, 256)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_read_cache(Some(ReadCache { max_age_ms: 10 }));
+    assert_eq!(driver.get_position().unwrap(), 2.0);
+    assert_eq!(driver.get_position().unwrap(), 2.0);
+    driver.set_position(0).unwrap();
+    assert_eq!(driver.get_position().unwrap(), 0.0);
+    driver.set_time_ms(10);
+    assert_eq!(driver.get_position().unwrap(), 0.0);
+    driver.set_time_ms(11);
+    assert_eq!(driver.get_position().unwrap(), 1.0);
+    mocks.done();
+}
+
+#[test]
+fn data_packet_display() {
+    let mut mocks = Expectations::new()
+        .status(0x28)
+        .read(Registers::XACTUAL, 0x200)
+        .build();
+    let mut driver = mocks.driver();
+    let packet = driver.read_register(Registers::XACTUAL).unwrap();
+    assert_eq!(
+        format!("{}", packet),
+        "0x00000200 standstill position_reached"
+    );
+    mocks.done();
+}
+
+#[test]
+fn reserved_bits_masked_or_rejected() {
+    let mut mocks = Expectations::new()
+        .write(Registers::RAMPMODE, 0x0000_0001)
+        .build();
+    let mut driver = mocks.driver().reserved_bits(ReservedBits::Mask);
+    let mut value = 0x0000_0005_u32.to_be_bytes();
+    driver
+        .write_register(Registers::RAMPMODE, &mut value)
+        .unwrap();
+    let mut driver = driver.reserved_bits(ReservedBits::Reject);
+    let mut value = 0x0000_0005_u32.to_be_bytes();
+    assert!(matches!(
+        driver.write_register(Registers::RAMPMODE, &mut value),
+        Err(Error::ReservedBits(0x20, 0x0000_0004))
+    ));
+    mocks.done();
+}
+
+#[test]
+fn tstep_cross_check() {
+    let mut mocks = Expectations::new()
+        .read(Registers::VACTUAL, (-1024_i32 as u32) & 0xFF_FFFF)
+        .read(Registers::TSTEP, 16_400)
+        .read(Registers::VACTUAL, 1024)
+        .read(Registers::TSTEP, 20_000)
+        .build();
+    let mut driver = mocks.driver();
+    let check = driver.check_tstep(0.05).unwrap();
+    assert_eq!(check.vactual, -1024);
+    assert_eq!(check.expected, Some(16_384.0));
+    assert!(!check.disagrees);
+    assert!(driver.check_tstep(0.05).unwrap().disagrees);
+    mocks.done();
+}
+
+#[test]
+fn health_monitor_counts_short_events() {
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0x0C00_0000)
+        .read(Registers::DRV_STATUS, 0x0800_0000)
+        .read(Registers::DRV_STATUS, 0)
+        .read(Registers::DRV_STATUS, 0x1000_0000)
+        .build();
+    let mut driver = mocks.driver();
+    let mut monitor = HealthMonitor::new();
+    for now_ms in [10, 20, 30, 40] {
+        monitor.tick(&mut driver, now_ms).unwrap();
+    }
+    let health = monitor.health();
+    assert_eq!(health.short_events, 2);
+    assert!(health.active.contains(FaultFlags::SHORT_TO_GROUND_B));
+    assert_eq!(health.ms_since_otpw(100), Some(90));
+    mocks.done();
+}
+
+#[test]
+fn event_counters() {
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0x0C00_0000)
+        .read(Registers::DRV_STATUS, 0x0C00_0000)
+        .status(0x01)
+        .read(Registers::DRV_STATUS, 0)
+        .read(Registers::DRV_STATUS, 0x1000_0000)
+        .build();
+    let mut driver = mocks.driver();
+    for _ in 0..4 {
+        driver.read_drv_status().unwrap();
+    }
+    let counters = driver.get_counters();
+    assert_eq!(counters.short_events, 2);
+    assert_eq!(counters.thermal_warnings, 1);
+    assert_eq!(counters.resets, 1);
+    driver.reset_counters();
+    assert_eq!(driver.get_counters().short_events, 0);
+    mocks.done();
+}
+
+#[test]
+fn tick_runs_duties_in_turn() {
+    let driver = Expectations::new().build().driver();
+    let chop_conf = u32::from_le_bytes(driver.chop_conf.into_bytes());
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0)
+        .read(Registers::GCONF, 0)
+        .read(Registers::CHOPCONF, chop_conf)
+        .read(Registers::DRV_STATUS, 0)
+        .build();
+    let mut driver = mocks.driver().tick_duties(TickDuties {
+        status: true,
+        watchdog: true,
+        ..TickDuties::default()
+    });
+    assert!(matches!(driver.tick(0), Ok(Some(TickDuty::Status(_)))));
+    assert_eq!(driver.tick(10).unwrap(), Some(TickDuty::Watchdog(false)));
+    assert!(matches!(driver.tick(20), Ok(Some(TickDuty::Status(_)))));
+    mocks.done();
+}
+
+#[test]
+fn tick_thermal_counters_and_cache() {
+    let mut mocks = Expectations::new()
+        .write(Registers::IHOLD_IRUN, 0x0006_1008)
+        .read(Registers::DRV_STATUS, 0x0400_0000)
+        .write(Registers::IHOLD_IRUN, 0x0006_0808)
+        .read(Registers::RAMP_STAT, 0)
+        .read(Registers::DRV_STATUS, 0x0400_0000)
+        .read(Registers::RAMP_STAT, 0)
+        .read(Registers::XACTUAL, 100)
+        .read(Registers::DRV_STATUS, 0)
+        .write(Registers::IHOLD_IRUN, 0x0006_1008)
+        .build();
+    let mut driver = mocks.driver().tick_duties(TickDuties {
+        thermal: Some(50),
+        counters: true,
+        cache: true,
+        ..TickDuties::default()
+    });
+    driver.set_currents(16, 8, 6).unwrap();
+    assert_eq!(driver.tick(0).unwrap(), Some(TickDuty::Thermal(true)));
+    match driver.tick(10).unwrap() {
+        Some(TickDuty::Counters(counters)) => assert_eq!(counters.thermal_warnings, 1),
+        duty => panic!("unexpected duty {:?}", duty),
+    }
+    assert_eq!(driver.tick(20).unwrap(), Some(TickDuty::Cache));
+    assert_eq!(driver.tick(30).unwrap(), Some(TickDuty::Thermal(false)));
+    assert_eq!(driver.get_torque_limit_percent(), 100);
+    mocks.done();
+}
+
+#[test]
+fn position_correction_at_standstill() {
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0x8000_0000)
+        .read(Registers::XACTUAL, 1000)
+        .read(Registers::X_ENC, 900)
+        .write(Registers::XACTUAL, 900)
+        .write(Registers::XTARGET, 900)
+        .read(Registers::DRV_STATUS, 0)
+        .build();
+    let mut driver = mocks.driver();
+    let mut correction = PositionCorrection::new(50, CorrectionPolicy::Resync);
+    let made = correction.update(&mut driver).unwrap().unwrap();
+    assert_eq!(made.deviation, 100);
+    assert_eq!(correction.update(&mut driver).unwrap(), None);
+    assert_eq!(correction.corrections(), 1);
+    mocks.done();
+}
+
+#[test]
+fn encoder_velocity_estimate() {
+    let mut mocks = Expectations::new()
+        .read(Registers::X_ENC, 1000)
+        .read(Registers::X_ENC, 1512)
+        .build();
+    let mut driver = mocks.driver();
+    assert_eq!(driver.estimate_encoder_velocity(10).unwrap(), None);
+    assert_eq!(driver.estimate_encoder_velocity(10).unwrap(), Some(200.0));
+    mocks.done();
+}
+
+#[test]
+fn electrical_state_from_default_table() {
+    let mut mocks = Expectations::new()
+        .read(Registers::MSCNT, 0)
+        .read(Registers::MSCURACT, 0x00F7_0000)
+        .build();
+    let mut driver = mocks.driver();
+    let state = driver.read_electrical_state().unwrap();
+    assert_eq!(state.expected, (0, 247));
+    assert!(state.matches());
+    let table = driver.microstep_table();
+    assert_eq!(table.currents(512), (0, -247));
+    assert_eq!(table.sine(1023), -1);
+    mocks.done();
+}
+
+#[test]
+fn sync_from_chip_populates_shadows() {
+    let mut mocks = Expectations::new()
+        .read(Registers::GCONF, 0x0000_0014)
+        .read(Registers::CHOPCONF, 0x1001_00C3)
+        .read(Registers::SW_MODE, 0x0000_0003)
+        .read(Registers::ENCMODE, 0)
+        .build();
+    let mut driver = mocks.driver();
+    driver.sync_from_chip().unwrap();
+    assert!(driver.g_conf.shaft());
+    assert_eq!(driver.chop_conf.toff(), 3);
+    assert!(driver.sw_mode.stop_l_enable());
+    mocks.done();
+}
+
+#[test]
+fn reset_to_defaults_requires_standstill() {
+    let mut mocks = Expectations::new().read(Registers::DRV_STATUS, 0).build();
+    let mut driver = mocks.driver();
+    assert!(matches!(
+        driver.reset_to_defaults(),
+        Err(Error::NotAtStandstill)
+    ));
+    mocks.done();
+}
+
+#[test]
+fn reset_to_defaults_writes_power_on_values() {
+    // OTP: otp_tbl, otp_bbm and otp_s2_level set
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0x8000_0000)
+        .read(Registers::OTP_READ, 0xE0)
+        // the ramp generator is stopped before anything else
+        .write(Registers::VMAX, 0)
+        .write(Registers::AMAX, 0)
+        .write(Registers::RAMPMODE, 0)
+        .write(Registers::GCONF, 0x0000_0008)
+        .write(Registers::SLAVECONF, 0)
+        .write(Registers::X_COMPARE, 0)
+        // S2VS_LEVEL and S2G_LEVEL 12 from OTP, SHORTFILTER 1
+        .write(Registers::SHORT_CONF, 0x0001_0C0C)
+        // BBMCLKS 2 from OTP, DRVSTRENGTH 2
+        .write(Registers::DRV_CONF, 0x0008_0200)
+        .write(Registers::GLOBALSCALER, 0)
+        .write(Registers::IHOLD_IRUN, 0)
+        .write(Registers::TPOWERDOWN, 10)
+        .write(Registers::TPWMTHRS, 0)
+        .write(Registers::TCOOLTHRS, 0)
+        .write(Registers::THIGH, 0)
+        // XACTUAL before XTARGET, so no move is started
+        .write(Registers::XACTUAL, 0)
+        .write(Registers::VSTART, 0)
+        .write(Registers::A1, 0)
+        .write(Registers::V1, 0)
+        .write(Registers::DMAX, 0)
+        .write(Registers::D1, 0)
+        .write(Registers::VSTOP, 0)
+        .write(Registers::TZEROWAIT, 0)
+        .write(Registers::XTARGET, 0)
+        .write(Registers::VDCMIN, 0)
+        .write(Registers::SW_MODE, 0)
+        .write(Registers::ENCMODE, 0)
+        .write(Registers::X_ENC, 0)
+        .write(Registers::ENC_CONST, 0x0001_0000)
+        .write(Registers::ENC_DEVIATION, 0)
+        .write(Registers::MSLUT_0_7, 0xAAAA_B554)
+        .write(Registers::MSLUT_1, 0x4A95_54AA)
+        .write(Registers::MSLUT_2, 0x2449_2929)
+        .write(Registers::MSLUT_3, 0x1010_4222)
+        .write(Registers::MSLUT_4, 0xFBFF_FFFF)
+        .write(Registers::MSLUT_5, 0xB5BB_777D)
+        .write(Registers::MSLUT_6, 0x4929_5556)
+        .write(Registers::MSLUT_7, 0x0040_4222)
+        .write(Registers::MSLUTSEL, 0xFFFF_8056)
+        .write(Registers::MSLUTSTART, 0x00F7_0000)
+        // TBL 1 from OTP
+        .write(Registers::CHOPCONF, 0x1040_8150)
+        .write(Registers::COOLCONF, 0)
+        .write(Registers::DCCTRL, 0)
+        .write(Registers::PWMCONF, 0xC40C_001E)
+        .write(Registers::GSTAT, 0b111)
+        .build();
+    let mut driver = mocks.driver();
+    driver.reset_to_defaults().unwrap();
+    assert_eq!(driver.chop_conf.tbl(), 1);
+    assert_eq!(driver.drv_conf.bbm_clks(), 2);
+    assert_eq!(driver.short_conf.s2vs_level(), 12);
+    assert_eq!(driver.short_conf.s2g_level(), 12);
+    assert!(driver.g_conf.multistep_filt());
+    mocks.done();
+}
+
+#[test]
+fn reboot_sequence() {
+    let mut mocks = Expectations::new()
+        .write(Registers::CHOPCONF, 0)
+        .write(Registers::GSTAT, 0b111)
+        .write(Registers::RAMP_STAT, 0x10CC)
+        .write(Registers::ENC_STATUS, 0b111)
+        .write(Registers::GCONF, 0)
+        .write(Registers::SLAVECONF, 0)
+        .write(Registers::SHORT_CONF, 0)
+        .write(Registers::DRV_CONF, 0)
+        .write(Registers::GLOBALSCALER, 0)
+        .write(Registers::IHOLD_IRUN, 0)
+        .write(Registers::COOLCONF, 0)
+        .write(Registers::PWMCONF, 0)
+        .write(Registers::DCCTRL, 0)
+        .write(Registers::SW_MODE, 0)
+        .write(Registers::ENCMODE, 0)
+        .write(Registers::CHOPCONF, 0)
+        .build();
+    let mut driver = mocks.driver();
+    driver.reboot().unwrap();
+    mocks.done();
+}
+
+#[test]
+fn enable_ramps_run_current() {
+    let mut mocks = Expectations::new()
+        .write(Registers::IHOLD_IRUN, 0x0006_1008)
+        .write(Registers::IHOLD_IRUN, 0x0006_0404)
+        .write(Registers::IHOLD_IRUN, 0x0006_0A08)
+        .write(Registers::IHOLD_IRUN, 0x0006_1008)
+        .build();
+    let mut en = PinMock::new(&[
+        PinTransaction::set(State::Low),
+        PinTransaction::set(State::Low),
+    ]);
+    let mut driver = mocks.driver().attach_en(en.clone());
+    driver.set_currents(16, 8, 6).unwrap();
+    driver
+        .set_enable_ramp(Some(EnableRamp {
+            start: 4,
+            duration_ms: 100,
+            steps: 2,
+        }))
+        .unwrap();
+    let mut delay = SumDelay(0);
+    driver.enable_with_ramp(&mut delay).unwrap();
+    assert_eq!(delay.0, 100);
+    // already enabled, no second ramp
+    driver.enable_with_ramp(&mut delay).unwrap();
+    assert_eq!(delay.0, 100);
+    mocks.done();
+    en.done();
+}
+
+struct NoDelay;
+
+impl DelayMs<u32> for NoDelay {
+    fn delay_ms(&mut self, _ms: u32) {}
+}
+
+struct SumDelay(u32);
+
+impl DelayMs<u32> for SumDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.0 += ms;
+    }
+}
+
+#[test]
+fn detect_motor_open_phase() {
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0x8000_0000)
+        .read(Registers::XACTUAL, 100)
+        .write(Registers::GCONF, 0x0001_0000)
+        .write(Registers::IHOLD_IRUN, 0x0000_0808)
+        .write(Registers::XTARGET, 0x00B5_00B5)
+        .read(Registers::DRV_STATUS, 0xA000_0000)
+        .write(Registers::XTARGET, 100)
+        .write(Registers::GCONF, 0)
+        .write(Registers::IHOLD_IRUN, 0)
+        .build();
+    let mut driver = mocks.driver();
+    driver.chop_conf.set_toff(3);
+    let detection = driver.detect_motor(8, &mut NoDelay).unwrap();
+    assert!(!detection.phase_a);
+    assert!(detection.phase_b);
+    assert!(!detection.is_connected());
+    mocks.done();
+}
+
+#[test]
+fn disconnect_detected_after_persistence() {
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0x2000_03E8)
+        .read(Registers::VACTUAL, 5000)
+        .read(Registers::DRV_STATUS, 0x2000_03E8)
+        .read(Registers::VACTUAL, 5000)
+        .read(Registers::DRV_STATUS, 0x2000_03E8)
+        .read(Registers::VACTUAL, 5000)
+        .build();
+    let mut driver = mocks.driver();
+    let mut monitor = DisconnectMonitor::new(1000, 100_000, 900, 500);
+    assert_eq!(monitor.update(&mut driver, 0).unwrap(), None);
+    let event = monitor.update(&mut driver, 600).unwrap().unwrap();
+    assert!(event.phase_a && !event.phase_b);
+    assert_eq!(monitor.update(&mut driver, 700).unwrap(), None);
+    mocks.done();
+}
+
+#[test]
+fn status_cache_follows_transactions() {
+    static CACHE: StatusCache = StatusCache::new();
+    let mut mocks = Expectations::new()
+        .status(0x08)
+        .read(Registers::XACTUAL, 1234)
+        .read(Registers::RAMP_STAT, 0x0180)
+        .write(Registers::RAMP_STAT, 0x80)
+        .write(Registers::XACTUAL, (-5_i32) as u32)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_status_cache(Some(&CACHE));
+    driver.read_register(Registers::XACTUAL).unwrap();
+    assert_eq!(CACHE.load().xactual, 1234);
+    assert!(CACHE.load().status.standstill());
+    driver.read_ramp_status().unwrap();
+    assert!(CACHE.load().ramp_stat.event_pos_reached());
+    driver
+        .write_register(Registers::RAMP_STAT, &mut 0x80_u32.to_be_bytes())
+        .unwrap();
+    let cached = CACHE.load();
+    assert!(!cached.ramp_stat.event_pos_reached());
+    assert!(cached.ramp_stat.velocity_reached());
+    driver
+        .write_register(Registers::XACTUAL, &mut (-5_i32).to_be_bytes())
+        .unwrap();
+    assert_eq!(CACHE.load().xactual, -5);
+    mocks.done();
+}
+
+#[test]
+fn standstill_mode_keeps_hold_current() {
+    let mut mocks = Expectations::new()
+        .write(Registers::IHOLD_IRUN, 0x0006_1008)
+        .write(Registers::IHOLD_IRUN, 0x0006_1000)
+        .write(Registers::PWMCONF, 0x0010_0000)
+        .write(Registers::IHOLD_IRUN, 0x0006_1008)
+        .write(Registers::PWMCONF, 0)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_currents(16, 8, 6).unwrap();
+    driver.set_standstill_mode(FreeWheel::Freewheel).unwrap();
+    assert!(driver.standstill_mode_is_active());
+    assert_eq!(driver.ihold_irun.i_hold(), 8);
+    driver.set_standstill_mode(FreeWheel::Normal).unwrap();
+    assert!(!driver.standstill_mode_is_active());
+    mocks.done();
+}
+
+#[test]
+fn rms_current_to_ihold_irun() {
+    let mut mocks = Expectations::new()
+        .write(Registers::IHOLD_IRUN, 0x0000_0F07)
+        .build();
+    let mut driver = mocks.driver();
+    let run = RmsCurrent::from_ma(1500).unwrap();
+    let hold = HoldPercent::new(50).unwrap();
+    assert!(matches!(
+        driver.set_rms_current(run, hold),
+        Err(Error::NotConfigured)
+    ));
+    let mut driver = driver.sense_resistor(0.075);
+    let too_high = RmsCurrent::from_ma(4000).unwrap();
+    assert!(matches!(
+        driver.set_rms_current(too_high, hold),
+        Err(Error::InvalidValue)
+    ));
+    driver.set_rms_current(run, hold).unwrap();
+    assert_eq!(driver.get_rms_current().unwrap().ma(), 1532);
+    assert!(HoldPercent::new(101).is_none());
+    mocks.done();
+}
+
+#[test]
+fn speed_profile_thresholds() {
+    let profile = SpeedProfile {
+        quiet: 100.0,
+        working: 200.0,
+        rapid: 1000.0,
+    };
+    let mut mocks = Expectations::new().build();
+    let mut driver = mocks.driver();
+    let planned = driver.plan_velocity_thresholds(profile).unwrap();
+    assert!(planned.tpwmthrs > planned.tcoolthrs && planned.tcoolthrs > planned.thigh);
+    let unordered = SpeedProfile {
+        working: 50.0,
+        ..profile
+    };
+    assert!(matches!(
+        driver.set_speed_profile(unordered),
+        Err(Error::InvalidValue)
+    ));
+    mocks.done();
+
+    let mut mocks = Expectations::new()
+        .write(Registers::TPWMTHRS, planned.tpwmthrs)
+        .write(Registers::TCOOLTHRS, planned.tcoolthrs)
+        .write(Registers::THIGH, planned.thigh)
+        .build();
+    let mut driver = mocks.driver();
+    assert_eq!(driver.set_speed_profile(profile).unwrap(), planned);
+    mocks.done();
+}
+
+#[test]
+fn stealthchop_saturation_after_persistence() {
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0x4000)
+        .read(Registers::PWM_SCALE, 0xFF)
+        .read(Registers::DRV_STATUS, 0x4000)
+        .read(Registers::PWM_SCALE, 0x01F0_00FF)
+        .read(Registers::VACTUAL, 0x00FF_FC18)
+        .read(Registers::DRV_STATUS, 0)
+        .read(Registers::PWM_SCALE, 0xFF)
+        .build();
+    let mut driver = mocks.driver();
+    let mut monitor = SaturationMonitor::new(255, 100);
+    assert_eq!(monitor.update(&mut driver, 0).unwrap(), None);
+    let event = monitor.update(&mut driver, 150).unwrap().unwrap();
+    assert_eq!(event.pwm_scale_sum, 255);
+    assert_eq!(event.pwm_scale_auto, -16);
+    assert_eq!(event.velocity, 1000);
+    assert_eq!(monitor.update(&mut driver, 200).unwrap(), None);
+    mocks.done();
+}
+
+#[test]
+fn mode_transitions_sync_target_and_restore_vmax() {
+    let mut mocks = Expectations::new()
+        .write(Registers::VMAX, 1000)
+        .write(Registers::VMAX, 0)
+        .read(Registers::DRV_STATUS, 0x8000_0000)
+        .read(Registers::XACTUAL, 500)
+        .write(Registers::XTARGET, 500)
+        .write(Registers::RAMPMODE, 0)
+        .write(Registers::VMAX, 1000)
+        .write(Registers::VMAX, 1000)
+        .write(Registers::RAMPMODE, 2)
+        .build();
+    let mut driver = mocks.driver();
+    driver.set_velocity_raw(1000).unwrap();
+    driver.set_vmax(0).unwrap();
+    driver.switch_to_positioning().unwrap();
+    assert!(matches!(
+        driver.switch_to_velocity(RampMode::PositioningMode),
+        Err(Error::InvalidValue)
+    ));
+    driver
+        .switch_to_velocity(RampMode::VelocityModeNeg)
+        .unwrap();
+    mocks.done();
+}
+
+#[test]
+fn enc_mode_helpers() {
+    let mode = EncMode::new()
+        .with_n_channel(true, NEventSensitivity::RisingEdge)
+        .with_ab_condition(Some((true, false)))
+        .with_clear(EncoderClear::Always, true)
+        .with_decimal_const(true);
+    assert_eq!(mode.n_event_sensitivity(), NEventSensitivity::RisingEdge);
+    assert_eq!(mode.clear(), Some(EncoderClear::Always));
+    let mut mocks = Expectations::new()
+        .write(Registers::ENCMODE, 0x0000_0555)
+        .build();
+    let mut driver = mocks.driver();
+    assert!(matches!(
+        driver.set_enc_mode(mode.with_clr_once(true)),
+        Err(Error::InvalidValue)
+    ));
+    assert!(matches!(
+        driver.set_enc_mode(mode.with_ignore_ab(true)),
+        Err(Error::InvalidValue)
+    ));
+    driver.set_enc_mode(mode).unwrap();
+    mocks.done();
+}
+
+#[test]
+fn slim_driver_alongside_full_driver() {
+    let mut mocks = Expectations::new()
+        .write(Registers::VMAX, 1000)
+        .read(Registers::XACTUAL, 42)
+        .build();
+    let mut driver: Tmc5160Slim<_, _, PinMock> =
+        Tmc5160Slim::new(mocks.spi.clone(), mocks.cs.clone());
+    driver
+        .write_register(Registers::VMAX, &mut 1000_u32.to_be_bytes())
+        .unwrap();
+    assert_eq!(driver.read_register(Registers::XACTUAL).unwrap().data, 42);
+    mocks.done();
+}
+
+#[test]
+fn dump_registers_reads_all_readable() {
+    let mut expectations = Expectations::new();
+    for register in Registers::READABLE {
+        expectations = expectations.read(register, register as u32);
+    }
+    let mut mocks = expectations.build();
+    let mut driver = mocks.driver();
+    let mut dumped = 0;
+    driver
+        .dump_registers(|register, value| {
+            assert_eq!(register, Registers::READABLE[dumped]);
+            assert_eq!(value, register as u32);
+            dumped += 1;
+        })
+        .unwrap();
+    assert_eq!(dumped, Registers::READABLE.len());
+    mocks.done();
+}
+
+#[test]
+fn readable_registers_follow_access() {
+    for register in Registers::ALL {
+        assert_eq!(
+            Registers::READABLE.contains(&register),
+            register.access().is_readable()
+        );
+    }
+    assert!(Registers::READABLE.contains(&Registers::IFCNT));
+}
+
+#[test]
+fn clock_trim_updates_conversion_clock() {
+    let mut mocks = Expectations::new()
+        .write(Registers::FACTORY_CONF, 14)
+        .build();
+    let mut driver = mocks.driver();
+    assert!(matches!(
+        driver.set_clock_trim(32, 12_000_000.0),
+        Err(Error::InvalidValue)
+    ));
+    assert!(matches!(
+        driver.set_clock_trim(14, 0.0),
+        Err(Error::InvalidValue)
+    ));
+    driver.set_clock_trim(14, 12_300_000.0).unwrap();
+    assert_eq!(driver.get_clock(), 12_300_000.0);
+    mocks.done();
+}
+
+#[test]
+fn telemetry_handle_is_copy() {
+    let mut mocks = Expectations::new()
+        .read(Registers::DRV_STATUS, 0x8000_0000)
+        .read(Registers::DRV_STATUS, 0)
+        .build();
+    let driver = core::cell::RefCell::new(mocks.driver());
+    let (_commands, telemetry) = split(&driver);
+    let copy = telemetry;
+    assert!(telemetry.read_drv_status().unwrap().standstill());
+    assert!(!copy.read_drv_status().unwrap().standstill());
+    mocks.done();
+}
+
+#[test]
+fn load_tracker_sums_large_windows() {
+    // 5M samples of SG_RESULT 1023 exceed a 32 bit sum
+    let window = 5_000_000;
+    let status = DrvStatus::from_value(0x001F_03FF);
+    let mut tracker = LoadTracker::new(window);
+    for _ in 1..window {
+        assert!(tracker.add(status).is_none());
+    }
+    let stats = tracker.add(status).unwrap();
+    assert_eq!(stats.samples, window);
+    assert!((stats.sg_result.average - 1023.0).abs() < 0.01);
+    assert!((stats.cs_actual.average - 31.0).abs() < 0.01);
+}